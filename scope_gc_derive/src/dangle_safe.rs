@@ -0,0 +1,49 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Error, Result};
+
+/// 展开 `#[derive(DangleSafe)]`
+///
+pub fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let field_types = match &input.data {
+        Data::Struct(data) => data.fields.iter().map(|f| &f.ty).collect::<Vec<_>>(),
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|v| v.fields.iter().map(|f| &f.ty))
+            .collect(),
+        Data::Union(data) => {
+            return Err(Error::new_spanned(
+                data.union_token,
+                "DangleSafe cannot be derived for unions",
+            ))
+        }
+    };
+
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+    for ty in field_types {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::scope_gc::dangle_safe::DangleSafe));
+    }
+
+    Ok(quote! {
+        // 如果类型实现了 `Drop`，两个实现将会冲突
+        const _: () = {
+            trait MustNotImplDrop {}
+
+            #[allow(drop_bounds)]
+            impl<T: ?::core::marker::Sized + ::core::ops::Drop> MustNotImplDrop for T {}
+
+            impl #impl_generics MustNotImplDrop for #name #ty_generics {}
+        };
+
+        unsafe impl #impl_generics ::scope_gc::dangle_safe::DangleSafe for #name #ty_generics
+        #where_clause
+        {
+        }
+    })
+}
@@ -0,0 +1,206 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::punctuated::Punctuated;
+use syn::{
+    parenthesized, parse_quote, DeriveInput, Error, FnArg, GenericParam, Ident, Pat, Result,
+    Signature, Token, Type,
+};
+
+/// `#[gc_target(...)]` 的参数
+///
+#[derive(Default)]
+pub struct Args {
+    refs: Option<Type>,
+    node_trait: Option<Ident>,
+    forward: Vec<Signature>,
+}
+
+impl Args {
+    pub fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("refs") {
+            self.refs = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("node_trait") {
+            self.node_trait = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("forward") {
+            let content;
+            parenthesized!(content in meta.input);
+            self.forward
+                .extend(Punctuated::<Signature, Token![,]>::parse_terminated(
+                    &content,
+                )?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported gc_target attribute"))
+        }
+    }
+}
+
+/// 展开 `#[gc_target(...)]`
+///
+pub fn expand(args: Args, item: DeriveInput) -> Result<TokenStream> {
+    let name = &item.ident;
+    let vis = &item.vis;
+    let refs = args.refs.unwrap_or_else(|| parse_quote!(()));
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    let this: Type = parse_quote!(#name #ty_generics);
+
+    let target = quote! {
+        impl #impl_generics ::scope_gc::target::Target for #this #where_clause {
+            type RefObject<'gc> = #refs;
+        }
+    };
+
+    let node_trait = match &args.node_trait {
+        Some(node_trait) => {
+            if item.generics.lifetimes().any(|l| l.lifetime.ident == "gc") {
+                return Err(Error::new_spanned(
+                    &item.generics,
+                    "the lifetime name 'gc is reserved by gc_target",
+                ));
+            }
+
+            let mut impl_generics = item.generics.clone();
+            impl_generics.params.insert(0, parse_quote!('gc));
+            // 被管理的值总是存活得比 `'gc` 更久
+            for param in impl_generics.params.iter_mut() {
+                if let GenericParam::Lifetime(l) = param {
+                    if l.lifetime.ident != "gc" {
+                        l.bounds.push(parse_quote!('gc));
+                    }
+                }
+            }
+            let (node_impl_generics, _, _) = impl_generics.split_for_impl();
+
+            let mut trait_generics = item.generics.clone();
+            trait_generics.params = impl_generics
+                .params
+                .iter()
+                .filter(|p| match p {
+                    GenericParam::Lifetime(l) => l.lifetime.ident == "gc",
+                    _ => true,
+                })
+                .cloned()
+                .collect();
+            let (trait_decl_generics, trait_ty_generics, _) = trait_generics.split_for_impl();
+            let trait_where = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+
+            // 类型的生命周期参数被替换为 `'gc`
+            let value_args = item.generics.params.iter().map(|p| match p {
+                GenericParam::Lifetime(_) => quote!('gc),
+                GenericParam::Type(t) => {
+                    let ident = &t.ident;
+                    quote!(#ident)
+                }
+                GenericParam::Const(c) => {
+                    let ident = &c.ident;
+                    quote!(#ident)
+                }
+            });
+            let value_ty: Type = if item.generics.params.is_empty() {
+                parse_quote!(#name)
+            } else {
+                parse_quote!(#name<#(#value_args),*>)
+            };
+            let value_decl = quote! {
+                /// 获取被管理的值，值的生命周期参数被缩短为 `'gc`
+                ///
+                fn value(&self) -> &#value_ty;
+            };
+            let value_impl = quote! {
+                #[inline(always)]
+                fn value(&self) -> &#value_ty {
+                    ::scope_gc::node::Node::value(self)
+                }
+            };
+
+            let mut forward_decls = Vec::new();
+            let mut forward_impls = Vec::new();
+            for sig in args.forward.iter() {
+                let ident = &sig.ident;
+                let mut inputs = sig.inputs.iter();
+                match inputs.next() {
+                    Some(FnArg::Receiver(r)) if r.reference.is_some() && r.mutability.is_none() => {
+                    }
+                    _ => {
+                        return Err(Error::new_spanned(
+                            sig,
+                            "forwarded methods must take `&self`",
+                        ))
+                    }
+                }
+                let mut args = Vec::new();
+                for input in inputs {
+                    match input {
+                        FnArg::Typed(t) => match &*t.pat {
+                            Pat::Ident(p) => args.push(&p.ident),
+                            _ => {
+                                return Err(Error::new_spanned(
+                                    &t.pat,
+                                    "forwarded arguments must be identifiers",
+                                ))
+                            }
+                        },
+                        FnArg::Receiver(r) => {
+                            return Err(Error::new_spanned(r, "unexpected receiver"))
+                        }
+                    }
+                }
+                forward_decls.push(quote!(#sig;));
+                forward_impls.push(quote! {
+                    #[inline(always)]
+                    #sig {
+                        <#this>::#ident(::scope_gc::node::Node::value(self), #(#args),*)
+                    }
+                });
+            }
+
+            quote! {
+                #vis trait #node_trait #trait_decl_generics: ::scope_gc::node::NodeTrait<'gc>
+                #trait_where
+                {
+                    #value_decl
+
+                    /// 获取引用集合
+                    ///
+                    fn ref_set(&self) -> &#refs;
+
+                    #(#forward_decls)*
+                }
+
+                impl #node_impl_generics #node_trait #trait_ty_generics
+                    for ::scope_gc::node::Node<'gc, #this>
+                #trait_where
+                {
+                    #value_impl
+
+                    #[inline(always)]
+                    fn ref_set(&self) -> &#refs {
+                        ::scope_gc::node::Node::ref_set(self)
+                    }
+
+                    #(#forward_impls)*
+                }
+            }
+        }
+        None => {
+            if !args.forward.is_empty() {
+                return Err(Error::new_spanned(
+                    &args.forward[0],
+                    "forward requires node_trait",
+                ));
+            }
+            quote!()
+        }
+    };
+
+    Ok(quote! {
+        #item
+
+        #target
+
+        #node_trait
+    })
+}
@@ -0,0 +1,62 @@
+//! `scope_gc` 的派生宏
+//!
+//! 通常通过 `scope_gc` 的 `derive` 特性使用
+//!
+
+mod dangle_safe;
+mod gc_target;
+mod ref_set;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// 为所有字段都实现了 `RefSet` 的结构体或枚举派生 `RefSet` 和 `Debug`
+///
+/// 如果类型带有生命周期参数，第一个生命周期参数被视为 `'gc`
+///
+/// 枚举在构造时使用标记了 `#[ref_set(default)]` 的变体，如果没有标记则使用第一个变体，追踪时只访问当前变体的字段
+///
+/// 如果需要自行实现 `Debug`，可以使用 `#[ref_set(no_debug)]` 跳过生成
+///
+#[proc_macro_derive(RefSet, attributes(ref_set))]
+pub fn derive_ref_set(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    ref_set::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// 为没有实现 `Drop` 且所有字段都实现了 `DangleSafe` 的结构体或枚举派生 `DangleSafe`
+///
+/// 如果类型实现了 `Drop`，将会产生实现冲突的编译错误
+///
+#[proc_macro_derive(DangleSafe)]
+pub fn derive_dangle_safe(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    dangle_safe::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// 为结构体或枚举生成 `Target` 实现，并可选地生成对应的节点特征
+///
+/// * `refs = T` 引用集合类型，可以使用 `'gc`，缺省为 `()`
+/// * `node_trait = NodeA` 生成的节点特征名，其泛型参数为 `'gc` 和类型自身的类型及常量参数
+/// * `forward(fn f(&self, ...) -> R, ...)` 需要转发到类型固有方法的方法签名
+///
+/// 节点特征包含 `ref_set` 和 `value` 访问方法，并为 `Node<'gc, T>` 实现
+///
+/// `value` 返回的值的生命周期参数被替换为 `'gc`，因此要求类型对其生命周期参数协变
+///
+/// 节点特征不包含类型的生命周期参数，因此引用集合可以引用 `dyn NodeA<'gc>` 自身
+///
+#[proc_macro_attribute]
+pub fn gc_target(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as DeriveInput);
+    let mut args = gc_target::Args::default();
+    let parser = syn::meta::parser(|meta| args.parse(meta));
+    parse_macro_input!(attr with parser);
+    gc_target::expand(args, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
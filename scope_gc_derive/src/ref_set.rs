@@ -0,0 +1,246 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, Attribute, Data, DeriveInput, Error, Fields, GenericParam, Generics, Ident,
+    Lifetime, LifetimeParam, Result, Type, Variant,
+};
+
+/// 展开 `#[derive(RefSet)]`
+///
+pub fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let (gc, generics) = gc_generics(&input.generics);
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut field_types = Vec::<&Type>::new();
+    let (build, collect, debug) = match &input.data {
+        Data::Struct(data) => {
+            field_types.extend(data.fields.iter().map(|f| &f.ty));
+            let pattern = pattern(&quote!(Self), &data.fields);
+            (
+                build(&gc, &quote!(Self), &data.fields),
+                collect(&pattern, &data.fields),
+                debug(&pattern, &name.to_string(), &data.fields),
+            )
+        }
+        Data::Enum(data) => {
+            let default = default_variant(name, data.variants.iter())?;
+            let mut collect_arms = Vec::new();
+            let mut debug_arms = Vec::new();
+            for variant in data.variants.iter() {
+                field_types.extend(variant.fields.iter().map(|f| &f.ty));
+                let ident = &variant.ident;
+                let pattern = pattern(&quote!(Self::#ident), &variant.fields);
+                collect_arms.push(collect(&pattern, &variant.fields));
+                debug_arms.push(debug(&pattern, &ident.to_string(), &variant.fields));
+            }
+            let ident = &default.ident;
+            (
+                build(&gc, &quote!(Self::#ident), &default.fields),
+                quote!(#(#collect_arms)*),
+                quote!(#(#debug_arms)*),
+            )
+        }
+        Data::Union(data) => {
+            return Err(Error::new_spanned(
+                data.union_token,
+                "RefSet cannot be derived for unions",
+            ))
+        }
+    };
+
+    let mut ref_set_where = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+    let mut debug_where = ref_set_where.clone();
+    for ty in field_types.iter() {
+        ref_set_where
+            .predicates
+            .push(parse_quote!(#ty: ::scope_gc::target::RefSet<#gc>));
+        debug_where
+            .predicates
+            .push(parse_quote!(#ty: ::std::fmt::Debug));
+    }
+
+    let debug_impl = if no_debug_attr(&input.attrs)? {
+        quote!()
+    } else {
+        let (debug_impl_generics, _, _) = input.generics.split_for_impl();
+        quote! {
+            impl #debug_impl_generics ::std::fmt::Debug for #name #ty_generics
+            #debug_where
+            {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    match self {
+                        #debug
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        unsafe impl #impl_generics ::scope_gc::target::RefSet<#gc> for #name #ty_generics
+        #ref_set_where
+        {
+            #[inline(always)]
+            unsafe fn build() -> Self {
+                #build
+            }
+
+            #[inline(always)]
+            unsafe fn collect(
+                &self,
+                stack: &mut ::std::vec::Vec<&dyn ::scope_gc::node::NodeTrait<#gc>>,
+            ) {
+                match self {
+                    #collect
+                }
+            }
+        }
+
+        #debug_impl
+    })
+}
+
+/// 确定 `'gc` 生命周期，如果类型没有生命周期参数则引入一个新的
+///
+fn gc_generics(generics: &Generics) -> (Lifetime, Generics) {
+    match generics.lifetimes().next() {
+        Some(param) => (param.lifetime.clone(), generics.clone()),
+        None => {
+            let gc = Lifetime::new("'__gc", Span::call_site());
+            let mut generics = generics.clone();
+            generics
+                .params
+                .insert(0, GenericParam::Lifetime(LifetimeParam::new(gc.clone())));
+            (gc, generics)
+        }
+    }
+}
+
+/// 是否通过 `#[ref_set(no_debug)]` 要求不生成 `Debug` 实现
+///
+fn no_debug_attr(attrs: &[Attribute]) -> Result<bool> {
+    let mut no_debug = false;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("ref_set")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("no_debug") {
+                no_debug = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported ref_set attribute"))
+            }
+        })?;
+    }
+    Ok(no_debug)
+}
+
+/// 选择构造时使用的变体
+///
+fn default_variant<'v>(
+    name: &Ident,
+    variants: impl Iterator<Item = &'v Variant> + Clone,
+) -> Result<&'v Variant> {
+    let mut marked = None;
+    for variant in variants.clone() {
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("ref_set"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    if marked.is_some() {
+                        return Err(meta.error("multiple variants marked as default"));
+                    }
+                    marked = Some(variant);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported ref_set attribute"))
+                }
+            })?;
+        }
+    }
+    match marked.or_else(|| variants.clone().next()) {
+        Some(variant) => Ok(variant),
+        None => Err(Error::new_spanned(
+            name,
+            "RefSet cannot be derived for enums without variants",
+        )),
+    }
+}
+
+/// 字段的绑定名
+///
+fn bindings(fields: &Fields) -> Vec<Ident> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| match &f.ident {
+            Some(ident) => format_ident!("__{}", ident),
+            None => format_ident!("__{}", i),
+        })
+        .collect()
+}
+
+/// 以引用方式解构全部字段的模式
+///
+fn pattern(path: &TokenStream, fields: &Fields) -> TokenStream {
+    let bindings = bindings(fields);
+    match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| &f.ident);
+            quote!(#path { #(#names: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(#path ( #(#bindings),* )),
+        Fields::Unit => quote!(#path),
+    }
+}
+
+fn build(gc: &Lifetime, path: &TokenStream, fields: &Fields) -> TokenStream {
+    let values = fields
+        .iter()
+        .map(|f| {
+            let ty = &f.ty;
+            quote!(<#ty as ::scope_gc::target::RefSet<#gc>>::build())
+        })
+        .collect::<Vec<_>>();
+    match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| &f.ident);
+            quote!(#path { #(#names: #values),* })
+        }
+        Fields::Unnamed(_) => quote!(#path ( #(#values),* )),
+        Fields::Unit => quote!(#path),
+    }
+}
+
+fn collect(pattern: &TokenStream, fields: &Fields) -> TokenStream {
+    let bindings = bindings(fields);
+    quote! {
+        #pattern => {
+            #(::scope_gc::target::RefSet::collect(#bindings, stack);)*
+        }
+    }
+}
+
+fn debug(pattern: &TokenStream, name: &str, fields: &Fields) -> TokenStream {
+    let bindings = bindings(fields);
+    match fields {
+        Fields::Named(named) => {
+            let names = named
+                .named
+                .iter()
+                .map(|f| f.ident.as_ref().unwrap().to_string());
+            quote! {
+                #pattern => f.debug_struct(#name)#(.field(#names, #bindings))*.finish(),
+            }
+        }
+        Fields::Unnamed(_) => quote! {
+            #pattern => f.debug_tuple(#name)#(.field(#bindings))*.finish(),
+        },
+        Fields::Unit => quote! {
+            #pattern => f.write_str(#name),
+        },
+    }
+}
@@ -0,0 +1,52 @@
+use scope_gc::gc::{scope_gc, Gc};
+use scope_gc_derive::DangleSafe;
+use std::cell::Cell;
+
+#[derive(DangleSafe)]
+struct View<'a> {
+    name: &'a str,
+    counts: Vec<&'a Cell<i32>>,
+}
+
+#[derive(DangleSafe)]
+enum Either<'a, T> {
+    Left(&'a T),
+    #[allow(dead_code)]
+    Right(Option<T>),
+}
+
+#[test]
+fn short_lived() {
+    let name = String::from("local");
+    let count = Cell::new(1);
+    scope_gc(Default::default(), |mut gc: Gc<'_, 'static>| {
+        let x = gc.new_short_lived(View {
+            name: &name,
+            counts: vec![&count, &count],
+        });
+        let y = gc.new_short_lived(Either::Left(&count));
+        count.set(2);
+        assert_eq!(x.value.0.name, "local");
+        assert_eq!(x.value.0.counts.iter().map(|c| c.get()).sum::<i32>(), 4);
+        match &y.value.0 {
+            Either::Left(c) => assert_eq!(c.get(), 2),
+            Either::Right(_) => unreachable!(),
+        }
+        drop(x);
+        drop(y);
+        gc.clear();
+        assert_eq!(gc.get_node_count(), 0);
+
+        {
+            let local = String::from("inner");
+            let z = gc.new_short_lived(View {
+                name: &local,
+                counts: Vec::new(),
+            });
+            assert_eq!(z.clone().value.0.name, "inner");
+        }
+        assert_eq!(gc.get_node_count(), 1);
+        gc.clear();
+        assert_eq!(gc.get_node_count(), 0);
+    });
+}
@@ -0,0 +1,78 @@
+use scope_gc::gc::scope_gc;
+use scope_gc::root_ref::RootRef;
+use scope_gc::strong_ref::StrongRef;
+use scope_gc_derive::gc_target;
+use std::fmt::Display;
+
+#[gc_target(
+    refs = StrongRef<'gc, dyn NodeA<'gc>>,
+    node_trait = NodeA,
+    forward(fn inner(&self) -> &i32, fn add(&self, x: i32) -> i32),
+)]
+struct A<'n>(&'n i32);
+
+impl<'n> A<'n> {
+    fn inner(&self) -> &i32 {
+        self.0
+    }
+
+    fn add(&self, x: i32) -> i32 {
+        self.0 + x
+    }
+}
+
+#[gc_target(node_trait = NodeB, forward(fn show(&self) -> String))]
+struct B<T: Display>(T);
+
+impl<T: Display> B<T> {
+    fn show(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[gc_target(node_trait = NodeC)]
+struct C<'a, 'b, T: Display>(&'a T, &'b str);
+
+#[gc_target]
+struct Plain;
+
+#[test]
+fn node_trait() {
+    let a = 1;
+    let b = 2;
+    scope_gc(Default::default(), |mut gc| {
+        let x: RootRef<dyn NodeA> = gc.new(A(&a)).coerce(|n| n as _);
+        let y: RootRef<dyn NodeA> = gc.new(A(&b)).coerce(|n| n as _);
+        x.ref_set().set_ref(&*y);
+        y.ref_set().set_ref(&*x);
+        assert_eq!(*x.inner(), 1);
+        assert_eq!(*x.value().0, 1);
+        assert_eq!(x.add(2), 3);
+        assert_eq!(*x.ref_set().get().unwrap().inner(), 2);
+        drop(y);
+        gc.clear();
+        assert_eq!(gc.get_node_count(), 2);
+        assert_eq!(*x.ref_set().get().unwrap().inner(), 2);
+    });
+}
+
+#[test]
+fn generic_target() {
+    scope_gc(Default::default(), |gc| {
+        let x: RootRef<dyn NodeB<i32>> = gc.new(B(5)).coerce(|n| n as _);
+        assert_eq!(x.show(), "5");
+        assert_eq!(x.value().0, 5);
+        let _: &() = gc.new(Plain).ref_set();
+    });
+}
+
+#[test]
+fn lifetime_value() {
+    let n = 3;
+    let s = String::from("c");
+    scope_gc(Default::default(), |gc| {
+        let x: RootRef<dyn NodeC<i32>> = gc.new(C(&n, &s)).coerce(|n| n as _);
+        let c: &C<i32> = x.value();
+        assert_eq!(format!("{}{}", c.1, c.0), "c3");
+    });
+}
@@ -0,0 +1,99 @@
+use scope_gc::gc::scope_gc;
+use scope_gc::node::Node;
+use scope_gc::once_ref::OnceRef;
+use scope_gc::strong_ref::StrongRef;
+use scope_gc::target::Target;
+use scope_gc_derive::RefSet;
+
+struct Item(i32);
+
+#[derive(RefSet)]
+struct ItemRefs<'gc> {
+    next: StrongRef<'gc, Node<'gc, Item>>,
+    first: OnceRef<'gc, Node<'gc, Item>>,
+    rest: (StrongRef<'gc, Node<'gc, Item>>, ()),
+}
+
+impl Target for Item {
+    type RefObject<'gc> = ItemRefs<'gc>;
+}
+
+#[derive(RefSet, Debug)]
+#[ref_set(no_debug)]
+struct Slots<'gc>(StrongRef<'gc, Node<'gc, Leaf>>);
+
+#[derive(RefSet)]
+enum Branch<'gc> {
+    #[allow(dead_code)]
+    Empty,
+    #[ref_set(default)]
+    One(StrongRef<'gc, Node<'gc, Fork>>),
+}
+
+#[derive(RefSet)]
+struct Unit;
+
+struct Fork;
+
+impl Target for Fork {
+    type RefObject<'gc> = Branch<'gc>;
+}
+
+struct Leaf;
+
+impl Target for Leaf {
+    type RefObject<'gc> = Slots<'gc>;
+}
+
+#[test]
+fn derive_struct() {
+    scope_gc(Default::default(), |mut gc| {
+        let x = gc.new(Item(0));
+        let y = gc.new(Item(1));
+        let z = gc.new(Item(2));
+        x.ref_set().next.set_ref(&y);
+        x.ref_set().first.init(&z).unwrap();
+        z.ref_set().rest.0.set_ref(&x);
+        drop(y);
+        drop(z);
+        gc.clear();
+        assert_eq!(gc.get_node_count(), 3);
+        assert_eq!(x.ref_set().next.get().unwrap().value().0, 1);
+        let debug = format!("{:?}", x.ref_set());
+        assert!(debug.starts_with("ItemRefs { next: StrongRef("));
+        assert!(debug.contains("first: OnceRef("));
+        drop(x);
+        gc.clear();
+        assert_eq!(gc.get_node_count(), 0);
+    });
+}
+
+#[test]
+fn derive_tuple_struct() {
+    scope_gc(Default::default(), |mut gc| {
+        let x = gc.new(Leaf);
+        let y = gc.new(Leaf);
+        x.ref_set().0.set_ref(&y);
+        drop(y);
+        gc.clear();
+        assert_eq!(gc.get_node_count(), 2);
+        assert!(format!("{:?}", x.ref_set()).starts_with("Slots(StrongRef("));
+        assert_eq!(format!("{:?}", Unit), "Unit");
+    });
+}
+
+#[test]
+fn derive_enum() {
+    scope_gc(Default::default(), |mut gc| {
+        let x = gc.new(Fork);
+        let y = gc.new(Fork);
+        match x.ref_set() {
+            Branch::One(r) => r.set_ref(&y),
+            Branch::Empty => unreachable!(),
+        }
+        drop(y);
+        gc.clear();
+        assert_eq!(gc.get_node_count(), 2);
+        assert!(format!("{:?}", x.ref_set()).starts_with("One(StrongRef("));
+    });
+}
@@ -0,0 +1,92 @@
+use crate::raw_type::RawType;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+/// 析构时不会访问任何借用数据的类型，相当于库层面的 `#[may_dangle]`
+///
+/// 实现此特征的值可以通过 [`crate::gc::Gc::new_short_lived`] 安全的托管，而不要求其存活时间
+///
+/// 使用 `derive` 特性提供的 `#[derive(DangleSafe)]` 为没有实现 [`Drop`] 且字段全部满足要求的类型派生
+///
+/// # 安全
+///
+/// 类型的所有生命周期参数在执行 [`Drop::drop`] 时允许悬空
+///
+/// 对于泛型容器，只有当其析构仅会析构内部的值时才可以在元素满足要求的情况下实现
+///
+pub unsafe trait DangleSafe {}
+
+macro_rules! impl_dangle_safe {
+    ($($T:ty),*) => {
+        $(unsafe impl DangleSafe for $T {})*
+    };
+}
+
+impl_dangle_safe!(
+    (),
+    bool,
+    char,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    f32,
+    f64,
+    str,
+    String
+);
+
+unsafe impl<T: ?Sized> DangleSafe for &T {}
+
+unsafe impl<T: ?Sized> DangleSafe for &mut T {}
+
+unsafe impl<T: ?Sized> DangleSafe for PhantomData<T> {}
+
+unsafe impl<T: ?Sized + DangleSafe> DangleSafe for RawType<T> {}
+
+unsafe impl<T: ?Sized + DangleSafe> DangleSafe for Box<T> {}
+
+unsafe impl<T: ?Sized + DangleSafe> DangleSafe for Rc<T> {}
+
+unsafe impl<T: ?Sized + DangleSafe> DangleSafe for Cell<T> {}
+
+unsafe impl<T: ?Sized + DangleSafe> DangleSafe for RefCell<T> {}
+
+unsafe impl<T: DangleSafe> DangleSafe for [T] {}
+
+unsafe impl<T: DangleSafe, const N: usize> DangleSafe for [T; N] {}
+
+unsafe impl<T: DangleSafe> DangleSafe for Vec<T> {}
+
+unsafe impl<T: DangleSafe> DangleSafe for VecDeque<T> {}
+
+unsafe impl<T: DangleSafe> DangleSafe for Option<T> {}
+
+macro_rules! impl_dangle_safe_for_tuple {
+    ($($T:ident),*) => {
+        unsafe impl<$($T: DangleSafe),*> DangleSafe for ($($T,)*) {}
+    };
+}
+
+impl_dangle_safe_for_tuple!(A);
+impl_dangle_safe_for_tuple!(A, B);
+impl_dangle_safe_for_tuple!(A, B, C);
+impl_dangle_safe_for_tuple!(A, B, C, D);
+impl_dangle_safe_for_tuple!(A, B, C, D, E);
+impl_dangle_safe_for_tuple!(A, B, C, D, E, F);
+impl_dangle_safe_for_tuple!(A, B, C, D, E, F, G);
+impl_dangle_safe_for_tuple!(A, B, C, D, E, F, G, H);
+impl_dangle_safe_for_tuple!(A, B, C, D, E, F, G, H, I);
+impl_dangle_safe_for_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_dangle_safe_for_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_dangle_safe_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
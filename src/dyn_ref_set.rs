@@ -0,0 +1,189 @@
+use crate::gc::NoGcGuard;
+use crate::gc_ref::GcRef;
+use crate::node::State::{Trace, Unknown};
+use crate::node::{NodeHead, NodeTrait};
+use crate::root_ref::RootRef;
+use crate::target::RefSet;
+use std::cell::RefCell;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::mem::{replace, transmute};
+use std::ptr::NonNull;
+
+struct Slot<'gc> {
+    name: Option<Box<str>>,
+    ptr: NonNull<dyn NodeTrait<'gc> + 'gc>,
+}
+
+/// 运行时决定形状的引用集合
+///
+/// 每个槽位可以引用任意类型的对象，并且可以具有名称
+///
+/// 可以配合 [`RootRef::downcast`] 恢复具体类型
+///
+pub struct DynRefSet<'gc> {
+    _marker: PhantomData<*mut &'gc ()>,
+    slots: RefCell<Vec<Slot<'gc>>>,
+}
+
+#[inline(always)]
+fn erase<'gc, T: ?Sized + NodeTrait<'gc> + 'gc>(r: &T) -> NonNull<dyn NodeTrait<'gc> + 'gc> {
+    // # 安全
+    //
+    // `T: 'gc`，被管理的对象总是存活于 `'gc` 期间
+    //
+    unsafe {
+        NonNull::from(transmute::<
+            &'_ (dyn NodeTrait<'gc> + '_),
+            &'_ (dyn NodeTrait<'gc> + 'gc),
+        >(r.as_dyn_node()))
+    }
+}
+
+impl<'gc> DynRefSet<'gc> {
+    /// 获取槽位数
+    ///
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.slots.borrow().len()
+    }
+
+    /// 是否没有任何槽位
+    ///
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.slots.borrow().is_empty()
+    }
+
+    /// 添加一个匿名槽位，返回其索引
+    ///
+    pub fn push<T: ?Sized + NodeTrait<'gc> + 'gc>(&self, r: &T) -> usize {
+        let mut slots = self.slots.borrow_mut();
+        slots.push(Slot {
+            name: None,
+            ptr: erase(r),
+        });
+        slots.len() - 1
+    }
+
+    /// 设置具名槽位，如果不存在则添加，返回其索引
+    ///
+    pub fn set_named<T: ?Sized + NodeTrait<'gc> + 'gc>(&self, name: &str, r: &T) -> usize {
+        let mut slots = self.slots.borrow_mut();
+        match slots.iter().position(|i| i.name.as_deref() == Some(name)) {
+            Some(index) => {
+                slots[index].ptr = erase(r);
+                index
+            }
+            None => {
+                slots.push(Slot {
+                    name: Some(name.into()),
+                    ptr: erase(r),
+                });
+                slots.len() - 1
+            }
+        }
+    }
+
+    /// 获取槽位引用的对象，如果索引越界则返回 [`None`]
+    ///
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Option<RootRef<'gc, dyn NodeTrait<'gc>>> {
+        self.slots
+            .borrow()
+            .get(index)
+            .map(|i| RootRef::new(unsafe { i.ptr.as_ref() }))
+    }
+
+    /// 在禁止回收期间获取不增加根引用计数的引用
+    ///
+    #[inline(always)]
+    pub fn get_ref<'r>(
+        &self,
+        index: usize,
+        _guard: &'r NoGcGuard<'_, 'gc, '_>,
+    ) -> Option<GcRef<'r, dyn NodeTrait<'gc> + 'gc>> {
+        self.slots
+            .borrow()
+            .get(index)
+            .map(|i| GcRef::new(unsafe { &*i.ptr.as_ptr() }))
+    }
+
+    /// 设置槽位引用的对象，返回原先引用的对象，如果索引越界则返回 [`None`]
+    ///
+    #[inline(always)]
+    pub fn set<T: ?Sized + NodeTrait<'gc> + 'gc>(
+        &self,
+        index: usize,
+        r: &T,
+    ) -> Option<RootRef<'gc, dyn NodeTrait<'gc>>> {
+        self.slots
+            .borrow_mut()
+            .get_mut(index)
+            .map(|i| RootRef::new(unsafe { replace(&mut i.ptr, erase(r)).as_ref() }))
+    }
+
+    /// 移除槽位，之后的槽位索引减一，如果索引越界则返回 [`None`]
+    ///
+    pub fn remove(&self, index: usize) -> Option<RootRef<'gc, dyn NodeTrait<'gc>>> {
+        let mut slots = self.slots.borrow_mut();
+        if index < slots.len() {
+            let slot = slots.remove(index);
+            Some(RootRef::new(unsafe { slot.ptr.as_ref() }))
+        } else {
+            None
+        }
+    }
+
+    /// 查找具名槽位的索引
+    ///
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.slots
+            .borrow()
+            .iter()
+            .position(|i| i.name.as_deref() == Some(name))
+    }
+
+    /// 获取具名槽位引用的对象
+    ///
+    pub fn get_named(&self, name: &str) -> Option<RootRef<'gc, dyn NodeTrait<'gc>>> {
+        self.index_of(name).and_then(|index| self.get(index))
+    }
+
+    /// 移除具名槽位，返回其引用的对象
+    ///
+    pub fn remove_named(&self, name: &str) -> Option<RootRef<'gc, dyn NodeTrait<'gc>>> {
+        self.index_of(name).and_then(|index| self.remove(index))
+    }
+}
+
+impl<'gc> Debug for DynRefSet<'gc> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_list();
+        for i in self.slots.borrow().iter() {
+            s.entry(&(&i.name, i.ptr));
+        }
+        s.finish()
+    }
+}
+
+unsafe impl<'gc> RefSet<'gc> for DynRefSet<'gc> {
+    #[inline(always)]
+    unsafe fn build() -> Self {
+        Self {
+            _marker: PhantomData,
+            slots: Default::default(),
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn collect(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+        for i in self.slots.borrow().iter() {
+            let r = &*i.ptr.as_ptr();
+            if NodeHead::from_node_trait(r).get_marker() == Unknown {
+                NodeHead::from_node_trait(r).set_marker(Trace);
+                stack.push(r);
+            }
+        }
+    }
+}
@@ -1,16 +1,27 @@
+use crate::dangle_safe::DangleSafe;
+use crate::gc_fn::GcFn;
+use crate::gc_ref::GcRef;
 use crate::node::State::{Strong, Trace, Unknown};
-use crate::node::{Node, NodeHead, NodeTrait};
+use crate::node::{CyclicNode, Node, NodeHead, NodeId, NodeTrait};
 use crate::raw_type::RawType;
 use crate::root_ref::RootRef;
-use crate::target::Target;
-use std::cell::RefCell;
+use crate::short_root_ref::ShortRootRef;
+use crate::target::{RefSet, Target};
+use crate::trace::{TraceType, Traced};
+use std::cell::{Cell, RefCell};
 use std::fmt::{Debug, Formatter};
 use std::marker::PhantomData;
-use std::mem::{swap, take, transmute, ManuallyDrop};
+use std::mem::{
+    align_of, align_of_val, forget, size_of, size_of_val, swap, take, transmute, ManuallyDrop,
+    MaybeUninit,
+};
 use std::ops::Deref;
-use std::ptr::NonNull;
+use std::ptr::{addr_of_mut, copy_nonoverlapping, drop_in_place, NonNull};
 
 /// 初始化 GC 多使用的配置项
+///
+/// 除了结构体字面量，也可以通过 [`Config::default`] 和 `with_*` 方法构造
+///
 #[derive(Copy, Clone, Debug)]
 pub struct Config {
     /// 是否执行预销毁
@@ -24,6 +35,9 @@ pub struct Config {
 
     /// 追踪可达性时使用的预分配栈大小因子
     pub stack_factor: f32,
+
+    /// 是否在作用域期间将 GC 设为当前线程的环境 GC，参见 [`with_current_gc`]
+    pub ambient: bool,
 }
 
 impl Default for Config {
@@ -33,22 +47,212 @@ impl Default for Config {
             init_cap: 32,
             forget_cap: 0,
             stack_factor: 0.1,
+            ambient: false,
         }
     }
 }
 
+impl Config {
+    /// 设置 [`Config::pre_drop`]
+    ///
+    pub fn with_pre_drop(self, pre_drop: bool) -> Self {
+        Self { pre_drop, ..self }
+    }
+
+    /// 设置 [`Config::init_cap`]
+    ///
+    pub fn with_init_cap(self, init_cap: usize) -> Self {
+        Self { init_cap, ..self }
+    }
+
+    /// 设置 [`Config::forget_cap`]
+    ///
+    pub fn with_forget_cap(self, forget_cap: usize) -> Self {
+        Self { forget_cap, ..self }
+    }
+
+    /// 设置 [`Config::stack_factor`]
+    ///
+    pub fn with_stack_factor(self, stack_factor: f32) -> Self {
+        Self {
+            stack_factor,
+            ..self
+        }
+    }
+
+    /// 设置 [`Config::ambient`]
+    ///
+    pub fn with_ambient(self, ambient: bool) -> Self {
+        Self { ambient, ..self }
+    }
+}
+
 /// 创建并使用一个 GC
 ///
 #[inline(always)]
 pub fn scope_gc<'s, F: for<'gc> FnOnce(Gc<'gc, 's>) -> R, R>(config: Config, f: F) -> R {
+    scope_gc_with(&mut GcStorage::new(), config, f)
+}
+
+/// 使用 `storage` 中保留的缓冲区创建并使用一个 GC
+///
+/// 作用域结束时，所有对象都会被销毁，缓冲区会被归还给 `storage` 以供下次使用
+///
+/// 即使 `f` 恐慌，对象也会在展开时被销毁
+///
+pub fn scope_gc_with<'s, F: for<'gc> FnOnce(Gc<'gc, 's>) -> R, R>(
+    storage: &mut GcStorage,
+    config: Config,
+    f: F,
+) -> R {
     unsafe {
-        let inner = RefCell::new(GcInner::new(config));
-        let r = f(Gc { inner: &inner });
-        inner.borrow_mut().clear_all();
-        r
+        let inner = RefCell::new(GcInner::new(config, storage));
+        let _guard = ScopeGuard {
+            storage,
+            inner: &inner,
+        };
+        // 先于 `_guard` 析构，销毁对象时环境 GC 已经恢复
+        let _ambient = config.ambient.then(|| AmbientGuard::install(&inner));
+        f(Gc { inner: &inner })
+    }
+}
+
+/// 异步地创建并使用一个 GC
+///
+/// GC 在 `f` 返回的异步块执行期间保持存活，可以跨越 `.await` 持有 [`RootRef`]，也可以在任意时刻执行 [`Gc::clear`]
+///
+/// 无论异步块执行完成还是被提前丢弃，所有对象都会被销毁
+///
+/// 由于多个任务可能在同一线程上交替执行，[`Config::ambient`] 在此被忽略
+///
+pub async fn scope_gc_async<'s, F: for<'gc> AsyncFnOnce(Gc<'gc, 's>) -> R, R>(
+    config: Config,
+    f: F,
+) -> R {
+    let mut storage = GcStorage::new();
+    let inner = RefCell::new(unsafe { GcInner::new(config, &mut storage) });
+    let _guard = ScopeGuard {
+        storage: &mut storage,
+        inner: &inner,
+    };
+    f(Gc { inner: &inner }).await
+}
+
+/// 作用域结束时销毁所有对象，并将缓冲区归还给 `storage`
+///
+struct ScopeGuard<'a, 'gc, 's: 'gc> {
+    storage: &'a mut GcStorage,
+    inner: &'gc RefCell<GcInner<'gc, 's>>,
+}
+
+impl<'a, 'gc, 's: 'gc> Drop for ScopeGuard<'a, 'gc, 's> {
+    fn drop(&mut self) {
+        unsafe {
+            let mut inner = self.inner.borrow_mut();
+            inner.clear_all();
+            inner.store(self.storage);
+        }
     }
 }
 
+thread_local! {
+    static CURRENT: Cell<Option<NonNull<RefCell<GcInner<'static, 'static>>>>> =
+        const { Cell::new(None) };
+}
+
+/// 在作用域期间设置当前线程的环境 GC，结束时恢复之前的环境 GC
+///
+struct AmbientGuard {
+    prev: Option<NonNull<RefCell<GcInner<'static, 'static>>>>,
+}
+
+impl AmbientGuard {
+    fn install<'gc, 's: 'gc>(inner: &'gc RefCell<GcInner<'gc, 's>>) -> Self {
+        let ptr = NonNull::from(inner).cast();
+        Self {
+            prev: CURRENT.with(|c| c.replace(Some(ptr))),
+        }
+    }
+}
+
+impl Drop for AmbientGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|c| c.set(self.prev));
+    }
+}
+
+/// 使用当前线程的环境 GC，如果没有则返回 [`None`]
+///
+/// 启用 [`Config::ambient`] 的最内层作用域会成为环境 GC
+///
+/// 环境 GC 正在回收时同样返回 [`None`]，例如在其对象的预析构或析构中调用
+///
+/// `f` 得到的句柄拥有独立的生命周期标识，因此从中托管的对象不会与其他句柄的对象混淆，并且值必须满足 `'static`
+///
+/// `f` 只能借用句柄，因此无法通过环境 GC 执行回收或创建 [`NoGcGuard`]
+///
+pub fn with_current_gc<R, F: for<'gc> FnOnce(&Gc<'gc, 'static>) -> R>(f: F) -> Option<R> {
+    // # 安全
+    //
+    // 环境 GC 只在其作用域内有效，而 `f` 的执行完全处于作用域内
+    // `'static` 的值总是满足原作用域的存活要求
+    //
+    let inner = unsafe { CURRENT.with(|c| c.get())?.as_ref() };
+    if inner.try_borrow_mut().is_err() {
+        return None;
+    }
+    Some(f(&Gc { inner }))
+}
+
+/// 可以在多个作用域之间复用的 GC 缓冲区，参见 [`scope_gc_with`]
+///
+/// 只保留缓冲区的容量，不会保留任何对象
+///
+#[derive(Default)]
+pub struct GcStorage {
+    nodes: Vec<NonNull<dyn NodeTrait<'static>>>,
+    forgets: Vec<NonNull<ManuallyDrop<dyn NodeTrait<'static>>>>,
+    slices: Vec<SliceNode<'static, 'static>>,
+    locals: Vec<NonNull<dyn NodeTrait<'static>>>,
+    stack: Vec<&'static dyn NodeTrait<'static>>,
+}
+
+impl GcStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 释放保留的全部缓冲区
+    ///
+    pub fn shrink(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Debug for GcStorage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcStorage")
+            .field("nodes", &self.nodes.capacity())
+            .field("forgets", &self.forgets.capacity())
+            .field("slices", &self.slices.capacity())
+            .field("locals", &self.locals.capacity())
+            .field("stack", &self.stack.capacity())
+            .finish()
+    }
+}
+
+/// 在仅有生命周期参数不同的元素类型之间转换空的缓冲区
+///
+/// # 安全
+///
+/// `T` 和 `U` 必须只有生命周期参数不同
+///
+unsafe fn recast<T, U>(v: Vec<T>) -> Vec<U> {
+    debug_assert!(v.is_empty());
+    let mut v = ManuallyDrop::new(v);
+    Vec::from_raw_parts(v.as_mut_ptr().cast::<U>(), 0, v.capacity())
+}
+
 /// 代表一个可以用于控制 GC 的句柄
 ///
 /// # 生命周期
@@ -57,7 +261,9 @@ pub fn scope_gc<'s, F: for<'gc> FnOnce(Gc<'gc, 's>) -> R, R>(config: Config, f:
 ///
 /// `'s` 安全托管所要求的值的存活时间，参见 [`Gc::new`]
 ///
-#[derive(Copy, Clone, Debug)]
+/// 句柄不能被复制，[`Gc::clear`] 需要独占句柄，因此存在 [`NoGcGuard`] 或 [`RootScope`] 时无法回收
+///
+#[derive(Debug)]
 pub struct Gc<'gc, 's: 'gc> {
     inner: &'gc RefCell<GcInner<'gc, 's>>,
 }
@@ -65,7 +271,7 @@ pub struct Gc<'gc, 's: 'gc> {
 impl<'gc, 's: 'gc> Gc<'gc, 's> {
     /// 托管一个值，该值必须实现 [`Target`]，并且存活时间久于 `'s`
     ///
-    pub fn new<T: Target + 's>(self, value: T) -> RootRef<'gc, Node<'gc, T>> {
+    pub fn new<T: Target + 's>(&self, value: T) -> RootRef<'gc, Node<'gc, T>> {
         unsafe { self.dangling(value) }
     }
 
@@ -75,17 +281,106 @@ impl<'gc, 's: 'gc> Gc<'gc, 's> {
     ///
     /// `T` 的所有生命周期参数在执行 [`Target::pre_drop`] 和 [`Drop::drop`] 时允许悬空
     ///
-    pub unsafe fn dangling<T: Target>(self, value: T) -> RootRef<'gc, Node<'gc, T>> {
-        let node = Box::new(Node::new(value));
-        let node_ref = transmute::<&'_ Node<'gc, T>, &'gc Node<'gc, T>>(node.deref());
-        self.inner
-            .borrow_mut()
-            .nodes
-            .push(NonNull::new_unchecked(Box::into_raw(transmute::<
-                Box<dyn NodeTrait<'gc> + 'gc>,
-                Box<dyn NodeTrait<'gc> + 's>,
-            >(node))));
-        RootRef::new(node_ref)
+    pub unsafe fn dangling<T: Target>(&self, value: T) -> RootRef<'gc, Node<'gc, T>> {
+        RootRef::new(self.manage(Box::new(Node::new(value, self.next_id()))))
+    }
+
+    /// 分配一个新的对象标识
+    ///
+    fn next_id(&self) -> NodeId {
+        self.inner.borrow_mut().next_id()
+    }
+
+    /// 将对象加入管理，返回的引用没有被根引用
+    ///
+    unsafe fn manage<T: Target>(&self, node: Box<Node<'gc, T>>) -> &'gc Node<'gc, T> {
+        self.inner.borrow_mut().manage(node)
+    }
+
+    /// 托管一个值，并在返回前使用 `f` 填充其引用集合
+    ///
+    /// 调用 `f` 时对象已经被根引用，`f` 内部执行的回收不会影响它
+    ///
+    pub fn new_with<T: Target + 's, F: FnOnce(&T::RefObject<'gc>)>(
+        &self,
+        value: T,
+        f: F,
+    ) -> RootRef<'gc, Node<'gc, T>> {
+        unsafe { self.dangling_with(value, f) }
+    }
+
+    /// 托管一个值，并在返回前使用 `f` 填充其引用集合，但不要求值的存活时间
+    ///
+    /// # 安全
+    ///
+    /// 参见 [`Gc::dangling`]
+    ///
+    pub unsafe fn dangling_with<T: Target, F: FnOnce(&T::RefObject<'gc>)>(
+        &self,
+        value: T,
+        f: F,
+    ) -> RootRef<'gc, Node<'gc, T>> {
+        let r = self.dangling(value);
+        f(r.ref_set());
+        r
+    }
+
+    /// 托管一个自身持有引用的值，参见 [`crate::trace::Trace`]
+    ///
+    /// 值内部的 [`crate::trace::TraceRef`] 从此由对象追踪，不再作为根引用
+    ///
+    pub fn new_traced<F: Traced + 's>(
+        &self,
+        value: F::Value<'gc>,
+    ) -> RootRef<'gc, Node<'gc, TraceType<F>>> {
+        self.new_with(TraceType::new(), |cell| cell.init(value))
+    }
+
+    /// 托管一个需要在构造期间引用自身的值，类似于 [`std::rc::Rc::new_cyclic`]
+    ///
+    /// `f` 接受尚未初始化完成的对象，并返回最终的值，期间对象的引用集合作为根参与回收
+    ///
+    /// 需要指向对象自身的引用通过 [`CyclicNode::link`] 登记，在值初始化并托管之后写入
+    ///
+    /// 如果 `f` 恐慌，对象的引用集合将被销毁并释放内存，登记的操作不会执行
+    ///
+    pub fn new_cyclic<T: Target + 's, F: FnOnce(&CyclicNode<'_, 'gc, T>) -> T>(
+        &self,
+        f: F,
+    ) -> RootRef<'gc, Node<'gc, T>> {
+        struct Guard<'a, 'gc, 's: 'gc, T: Target>(&'a Gc<'gc, 's>, *mut Node<'gc, T>);
+
+        impl<'a, 'gc, 's: 'gc, T: Target> Drop for Guard<'a, 'gc, 's, T> {
+            fn drop(&mut self) {
+                unsafe {
+                    self.0.inner.borrow_mut().building.pop();
+                    drop_in_place(addr_of_mut!((*self.1).ref_set));
+                    drop(Box::from_raw(self.1.cast::<MaybeUninit<Node<'gc, T>>>()));
+                }
+            }
+        }
+
+        unsafe {
+            let ptr = Box::into_raw(Box::new(MaybeUninit::<Node<'gc, T>>::uninit())).cast();
+            Node::init_uninit(ptr, self.next_id());
+            // `guard` 先于 `cyclic` 声明，恐慌时登记的操作会在释放对象之前销毁
+            let guard = Guard(self, ptr);
+            let cyclic = CyclicNode::new(ptr);
+            self.inner
+                .borrow_mut()
+                .building
+                .push(NonNull::from(transmute::<
+                    &'_ (dyn NodeTrait<'gc> + '_),
+                    &'gc (dyn NodeTrait<'gc> + 'gc),
+                >(cyclic.as_dyn_node())));
+            let value = f(&cyclic);
+            forget(guard);
+            self.inner.borrow_mut().building.pop();
+            Node::init_value(ptr, value);
+            let r = RootRef::new(self.manage(Box::from_raw(ptr)));
+            cyclic.finish(&r);
+            r
+        }
     }
 
     /// 托管一个值，该值必须实现 [`Target`]，但不要求值的存活时间
@@ -94,30 +389,61 @@ impl<'gc, 's: 'gc> Gc<'gc, 's> {
     ///
     /// 其行为就好像是一旦值不可达，立即调用 [`std::mem::forget`] 将值遗忘
     ///
-    pub fn forget<T: Target>(self, value: T) -> RootRef<'gc, Node<'gc, T>> {
+    pub fn forget<T: Target>(&self, value: T) -> RootRef<'gc, Node<'gc, T>> {
         unsafe {
-            let node = Box::new(ManuallyDrop::new(Node::new(value)));
-            let node_ref = transmute::<&'_ Node<'gc, T>, &'gc Node<'gc, T>>(node.deref());
-            self.inner
-                .borrow_mut()
-                .forgets
-                .push(NonNull::new_unchecked(Box::into_raw(transmute::<
-                    Box<ManuallyDrop<dyn NodeTrait<'gc> + 'gc>>,
-                    Box<ManuallyDrop<dyn NodeTrait<'gc> + 's>>,
-                >(
-                    node
-                ))));
-            RootRef::new(node_ref)
+            let mut inner = self.inner.borrow_mut();
+            let id = inner.next_id();
+            RootRef::new(inner.forget(Box::new(ManuallyDrop::new(Node::new(value, id)))))
         }
     }
 
+    /// 托管迭代器产生的全部值，参见 [`Gc::new`]
+    ///
+    /// 所有对象在一次借用中加入管理，并且只预留一次存储空间
+    ///
+    pub fn new_many<T: Target + 's, I: IntoIterator<Item = T>>(
+        &self,
+        iter: I,
+    ) -> Vec<RootRef<'gc, Node<'gc, T>>> {
+        let values = iter.into_iter().collect::<Vec<T>>();
+        let mut inner = self.inner.borrow_mut();
+        inner.nodes.reserve(values.len());
+        values
+            .into_iter()
+            .map(|value| unsafe {
+                let id = inner.next_id();
+                RootRef::new(inner.manage(Box::new(Node::new(value, id))))
+            })
+            .collect()
+    }
+
+    /// 以 [`Gc::forget`] 的方式托管迭代器产生的全部值
+    ///
+    /// 所有对象在一次借用中加入管理，并且只预留一次存储空间
+    ///
+    pub fn forget_many<T: Target, I: IntoIterator<Item = T>>(
+        &self,
+        iter: I,
+    ) -> Vec<RootRef<'gc, Node<'gc, T>>> {
+        let values = iter.into_iter().collect::<Vec<T>>();
+        let mut inner = self.inner.borrow_mut();
+        inner.forgets.reserve(values.len());
+        values
+            .into_iter()
+            .map(|value| unsafe {
+                let id = inner.next_id();
+                RootRef::new(inner.forget(Box::new(ManuallyDrop::new(Node::new(value, id)))))
+            })
+            .collect()
+    }
+
     /// 使用 [`RawType`] 包裹并调用 [`Gc::new`]
     ///
     /// [`RawType`] 将 [`Target`] 的 `RefObject` 实现为 `()`，因此不具有引用其他被管理对象的能力
     ///
     /// 优先考虑使用 [`std::rc::Rc`]
     ///
-    pub fn new_raw<T: 's>(self, value: T) -> RootRef<'gc, Node<'gc, RawType<T>>> {
+    pub fn new_raw<T: 's>(&self, value: T) -> RootRef<'gc, Node<'gc, RawType<T>>> {
         self.new(RawType(value))
     }
 
@@ -129,39 +455,224 @@ impl<'gc, 's: 'gc> Gc<'gc, 's> {
     ///
     /// `T` 的所有生命周期参数在执行 [`Drop::drop`] 时允许悬空
     ///
-    pub unsafe fn dangling_raw<T>(self, value: T) -> RootRef<'gc, Node<'gc, RawType<T>>> {
+    pub unsafe fn dangling_raw<T>(&self, value: T) -> RootRef<'gc, Node<'gc, RawType<T>>> {
         self.dangling(RawType(value))
     }
 
+    /// 使用 [`RawType`] 包裹并托管一个存活时间较短的值
+    ///
+    /// 与 [`Gc::dangling_raw`] 类似，但由 [`DangleSafe`] 保证析构时不会访问悬空的借用
+    ///
+    /// 值甚至可以短于 `'gc`，因此返回不要求 `T: 'gc` 的 [`ShortRootRef`]
+    ///
+    pub fn new_short_lived<T: DangleSafe>(
+        &self,
+        value: T,
+    ) -> ShortRootRef<'gc, Node<'gc, RawType<T>>> {
+        unsafe {
+            let mut inner = self.inner.borrow_mut();
+            let id = inner.next_id();
+            let node = Box::new(Node::new(RawType(value), id));
+            let r = ShortRootRef::new(&*node);
+            inner
+                .nodes
+                .push(NonNull::new_unchecked(Box::into_raw(transmute::<
+                    Box<dyn NodeTrait<'gc> + '_>,
+                    Box<dyn NodeTrait<'gc> + 's>,
+                >(
+                    node
+                ))));
+            r
+        }
+    }
+
     /// 使用 [`RawType`] 包裹并调用 [`Gc::forget`]
     ///
     /// 优先考虑使用 [`std::rc::Rc`]
     ///
-    pub fn forget_raw<T>(self, value: T) -> RootRef<'gc, Node<'gc, RawType<T>>> {
+    pub fn forget_raw<T>(&self, value: T) -> RootRef<'gc, Node<'gc, RawType<T>>> {
         self.forget(RawType(value))
     }
 
+    /// 复制切片并托管，切片元素直接存储在对象内部
+    ///
+    pub fn new_slice_copy<T: Copy + 's>(&self, s: &[T]) -> RootRef<'gc, Node<'gc, RawType<[T]>>> {
+        unsafe {
+            let ptr = Node::<'gc, RawType<[T]>>::alloc_slice(s.len(), self.next_id());
+            copy_nonoverlapping(s.as_ptr(), Node::slice_data(ptr), s.len());
+            RootRef::new(self.manage_slice(ptr))
+        }
+    }
+
+    /// 复制字符串并托管，字符串内容直接存储在对象内部
+    ///
+    pub fn new_str(&self, s: &str) -> RootRef<'gc, Node<'gc, RawType<str>>> {
+        unsafe {
+            let ptr = Node::<'gc, RawType<[u8]>>::alloc_slice(s.len(), self.next_id());
+            copy_nonoverlapping(s.as_ptr(), Node::slice_data(ptr), s.len());
+            let node = self.manage_slice(ptr);
+            RootRef::new(
+                &*(node as *const Node<'gc, RawType<[u8]>> as *const Node<'gc, RawType<str>>),
+            )
+        }
+    }
+
+    /// 收集迭代器的全部元素并托管为切片，切片元素直接存储在对象内部
+    ///
+    /// 对象被回收时，元素的 [`Drop::drop`] 会被调用
+    ///
+    pub fn new_from_iter<T: 's, I: IntoIterator<Item = T>>(
+        &self,
+        iter: I,
+    ) -> RootRef<'gc, Node<'gc, RawType<[T]>>> {
+        let mut values = iter.into_iter().collect::<Vec<T>>();
+        unsafe {
+            let ptr = Node::<'gc, RawType<[T]>>::alloc_slice(values.len(), self.next_id());
+            copy_nonoverlapping(values.as_ptr(), Node::slice_data(ptr), values.len());
+            values.set_len(0);
+            RootRef::new(self.manage_slice(ptr))
+        }
+    }
+
+    /// 将切片对象加入管理，返回的引用没有被根引用
+    ///
+    unsafe fn manage_slice<T: 's>(
+        &self,
+        ptr: *mut Node<'gc, RawType<[T]>>,
+    ) -> &'gc Node<'gc, RawType<[T]>> {
+        let node = &*ptr;
+        self.inner.borrow_mut().slices.push(SliceNode {
+            node: NonNull::from(transmute::<
+                &'_ (dyn NodeTrait<'gc> + '_),
+                &'_ (dyn NodeTrait<'gc> + 's),
+            >(node.as_dyn_node())),
+            len: node.value.0.len(),
+            drop: Node::<'gc, RawType<[T]>>::drop_slice,
+        });
+        node
+    }
+
+    /// 托管一个值，并以动态大小类型 `D` 的形式访问，例如特征对象
+    ///
+    /// 值直接存储在对象内部，不需要额外的 [`Box`]
+    ///
+    /// `coerce` 必须返回经过非固定大小转换的 `v` 自身，例如 `|v| v as &dyn Debug`
+    ///
+    /// # 恐慌
+    ///
+    /// 如果 `coerce` 返回的引用不指向 `v`，或者其大小和对齐与 `V` 不同，例如返回 `v` 位于起始地址的字段，则恐慌
+    ///
+    pub fn new_dyn<V: 's, D: ?Sized, F: FnOnce(&V) -> &D>(
+        &self,
+        value: V,
+        coerce: F,
+    ) -> RootRef<'gc, Node<'gc, RawType<D>>> {
+        let node = self.new_raw(value);
+        let node_ptr = &*node as *const Node<'gc, RawType<V>>;
+        let value = coerce(&node.value.0);
+        assert!(
+            value as *const D as *const u8 == &node.value.0 as *const V as *const u8
+                && size_of_val(value) == size_of::<V>()
+                && align_of_val(value) == align_of::<V>(),
+            "coerce must return the given reference"
+        );
+        let value_ptr = value as *const D;
+        unsafe {
+            let offset = (value_ptr as *const u8).offset_from(node_ptr as *const u8) as usize;
+            let ptr = value_ptr.byte_sub(offset) as *const Node<'gc, RawType<D>>;
+            RootRef::new(&*ptr)
+        }
+    }
+
+    /// 获取一个禁止回收的守卫
+    ///
+    /// 守卫存在期间，可以从引用集合中获得不增加根引用计数的 [`crate::gc_ref::GcRef`]
+    ///
+    /// 守卫独占地借用句柄，因此守卫存在期间无法调用 [`Gc::clear`]，需要分配对象时使用 [`NoGcGuard::gc`]
+    ///
+    /// ```compile_fail
+    /// scope_gc::gc::scope_gc(Default::default(), |mut gc| {
+    ///     let guard = gc.no_gc();
+    ///     gc.clear();
+    ///     drop(guard);
+    /// });
+    /// ```
+    ///
+    pub fn no_gc(&mut self) -> NoGcGuard<'_, 'gc, 's> {
+        NoGcGuard { gc: self }
+    }
+
+    /// 创建一个根作用域，作用域内创建的句柄会在作用域结束时统一释放
+    ///
+    /// 参见 [`RootScope`]
+    ///
+    /// 作用域独占地借用句柄，期间通过 [`RootScope::clear`] 执行回收
+    ///
+    pub fn root_scope<R, F: for<'r> FnOnce(&'r RootScope<'r, 'gc, 's>) -> R>(&mut self, f: F) -> R {
+        RootScope::enter(self, f)
+    }
+
+    /// 创建一个嵌套 GC，其中的值只需要存活时间久于 `'l`，而 `'l` 不会长于外部 GC
+    ///
+    /// 嵌套 GC 拥有独立的生命周期标识，因此外部对象的引用集合无法持有指向其中对象的引用，反之亦然
+    ///
+    /// 嵌套 GC 沿用外部的配置，但总是执行预析构，`f` 返回时其中的所有对象都会被销毁，包括预析构和析构
+    ///
+    /// 嵌套 GC 不会成为环境 GC，[`with_current_gc`] 在 `f` 中依然使用外部的环境 GC
+    ///
+    pub fn sub_scope<'l, R, F: for<'sub> FnOnce(Gc<'sub, 'l>) -> R>(&self, f: F) -> R
+    where
+        'gc: 'l,
+    {
+        let config = Config {
+            pre_drop: true,
+            ambient: false,
+            ..self.inner.borrow().config
+        };
+        scope_gc(config, f)
+    }
+
+    /// 托管一个闭包，闭包捕获的被管理对象通过 `init` 写入引用集合
+    ///
+    /// 调用时闭包会得到引用集合和参数，参见 [`GcFn`]
+    ///
+    /// 引用集合的类型 `S` 由闭包的参数推断，例如 `gc.new_fn(|env: &StrongRef<_>| ..., |env, x| ...)`
+    ///
+    pub fn new_fn<S, A, R, I, F>(&self, init: I, f: F) -> RootRef<'gc, Node<'gc, GcFn<'gc, S, F>>>
+    where
+        S: RefSet<'gc>,
+        I: FnOnce(&S),
+        F: Fn(&S, A) -> R + 's,
+    {
+        // # 安全
+        //
+        // `F` 满足 `'s`，而 `S` 作为引用集合允许 `'gc` 在析构时悬空
+        //
+        unsafe { self.dangling_with(GcFn::new(f), |env| init(env.env())) }
+    }
+
     /// 确保剩余容量大于 `cap`
     ///
-    pub fn reserve(self, cap: usize) {
+    pub fn reserve(&self, cap: usize) {
         self.inner.borrow_mut().nodes.reserve(cap);
     }
 
     /// 确保剩余用于储存 [`Gc::forget`] 的容量大于 `cap`
     ///
-    pub fn reserve_forgets(self, cap: usize) {
+    pub fn reserve_forgets(&self, cap: usize) {
         self.inner.borrow_mut().forgets.reserve(cap);
     }
 
     /// 获取当前管理的对象数
     ///
-    pub fn get_node_count(self) -> usize {
-        self.inner.borrow().nodes.len()
+    pub fn get_node_count(&self) -> usize {
+        let inner = self.inner.borrow();
+        inner.nodes.len() + inner.slices.len()
     }
 
     /// 获取当前管理的 [`Gc::forget`] 对象数
     ///
-    pub fn get_forget_count(self) -> usize {
+    pub fn get_forget_count(&self) -> usize {
         self.inner.borrow().forgets.len()
     }
 
@@ -171,13 +682,168 @@ impl<'gc, 's: 'gc> Gc<'gc, 's> {
     ///
     /// 如果不可达对象以 [`Gc::forget`] 方式加入，则仅仅回收内存
     ///
-    pub fn clear(self) {
+    /// 回收需要独占句柄，因此不会与 [`NoGcGuard`] 或 [`RootScope`] 同时存在
+    ///
+    pub fn clear(&mut self) {
+        unsafe { self.inner.borrow_mut().clear() }
+    }
+}
+
+/// 禁止回收的守卫，参见 [`Gc::no_gc`]
+///
+pub struct NoGcGuard<'a, 'gc, 's: 'gc> {
+    gc: &'a Gc<'gc, 's>,
+}
+
+impl<'a, 'gc, 's: 'gc> NoGcGuard<'a, 'gc, 's> {
+    /// 获取对应的 GC 句柄，通过它可以分配对象，但不能执行回收
+    ///
+    pub fn gc(&self) -> &'a Gc<'gc, 's> {
+        self.gc
+    }
+}
+
+impl<'a, 'gc, 's: 'gc> Debug for NoGcGuard<'a, 'gc, 's> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NoGcGuard").finish()
+    }
+}
+
+/// 根作用域，参见 [`Gc::root_scope`]
+///
+/// 作用域持有的句柄不修改对象的根引用计数，而是登记在作用域的根列表中，回收时统一枚举
+///
+/// 句柄可以通过 [`GcRef::root`] 提升为 [`RootRef`] 以逃出作用域
+///
+/// 每个作用域拥有独立的根列表，嵌套作用域结束时不会影响外层作用域登记的对象
+///
+pub struct RootScope<'a, 'gc, 's: 'gc> {
+    gc: &'a Gc<'gc, 's>,
+    roots: Roots<'gc>,
+}
+
+/// 根作用域登记的对象
+///
+type Roots<'gc> = RefCell<Vec<NonNull<dyn NodeTrait<'gc> + 'gc>>>;
+
+impl<'a, 'gc, 's: 'gc> RootScope<'a, 'gc, 's> {
+    /// 创建作用域并将其根列表登记到 GC 中，`f` 返回后注销
+    ///
+    fn enter<R, F: for<'r> FnOnce(&'r RootScope<'r, 'gc, 's>) -> R>(
+        gc: &'a Gc<'gc, 's>,
+        f: F,
+    ) -> R {
+        let roots = take(&mut gc.inner.borrow_mut().locals);
+        let scope = RootScope {
+            gc,
+            roots: RefCell::new(roots),
+        };
+        gc.inner
+            .borrow_mut()
+            .scopes
+            .push(NonNull::from(&scope.roots));
+        f(&scope)
+    }
+
+    /// 创建一个嵌套的根作用域，参见 [`Gc::root_scope`]
+    ///
+    /// 嵌套作用域结束时只释放自身登记的对象，期间仍然可以向外层作用域登记对象
+    ///
+    pub fn root_scope<R, F: for<'r> FnOnce(&'r RootScope<'r, 'gc, 's>) -> R>(&self, f: F) -> R {
+        RootScope::enter(self.gc, f)
+    }
+
+    /// 获取对应的 GC 句柄，通过它可以分配对象，但不能执行回收
+    ///
+    pub fn gc(&self) -> &'a Gc<'gc, 's> {
+        self.gc
+    }
+
+    /// 执行回收，作用域中登记的对象都被视为根，参见 [`Gc::clear`]
+    ///
+    pub fn clear(&self) {
+        // # 安全
+        //
+        // 作用域独占句柄，此时不存在 [`NoGcGuard`]，作用域产生的 [`GcRef`] 都已登记
+        //
+        unsafe { self.gc.inner.borrow_mut().clear() }
+    }
+
+    /// 将对象登记到作用域中
+    ///
+    pub fn root<'r, T: ?Sized + NodeTrait<'gc> + 'gc>(&'r self, r: &T) -> GcRef<'r, T> {
         unsafe {
-            let mut inner = self.inner.borrow_mut();
+            self.roots.borrow_mut().push(NonNull::from(transmute::<
+                &'_ (dyn NodeTrait<'gc> + '_),
+                &'gc (dyn NodeTrait<'gc> + 'gc),
+            >(r.as_dyn_node())));
+            GcRef::new(transmute::<&'_ T, &'r T>(r))
+        }
+    }
 
-            let mut stack = Vec::with_capacity(
-                ((inner.nodes.len() + inner.forgets.len()) as f32 * inner.config.stack_factor)
-                    as usize,
+    /// 托管一个值，并将其登记到作用域中，参见 [`Gc::new`]
+    ///
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<T: Target + 's>(&self, value: T) -> GcRef<'_, Node<'gc, T>> {
+        unsafe {
+            let node = self
+                .gc
+                .manage(Box::new(Node::new(value, self.gc.next_id())));
+            self.root(node)
+        }
+    }
+}
+
+impl<'a, 'gc, 's: 'gc> Drop for RootScope<'a, 'gc, 's> {
+    fn drop(&mut self) {
+        let mut inner = self.gc.inner.borrow_mut();
+        let scope = inner.scopes.pop();
+        debug_assert_eq!(scope, Some(NonNull::from(&self.roots)));
+        // 归还缓冲区以供之后的作用域复用
+        let mut roots = take(self.roots.get_mut());
+        roots.clear();
+        if roots.capacity() > inner.locals.capacity() {
+            inner.locals = roots;
+        }
+    }
+}
+
+impl<'a, 'gc, 's: 'gc> Debug for RootScope<'a, 'gc, 's> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RootScope")
+            .field("roots", &self.roots.borrow().len())
+            .finish()
+    }
+}
+
+struct GcInner<'gc, 's: 'gc> {
+    _marker: PhantomData<*mut &'gc ()>,
+    config: Config,
+    nodes: Vec<NonNull<dyn NodeTrait<'gc> + 's>>,
+    forgets: Vec<NonNull<ManuallyDrop<dyn NodeTrait<'gc> + 's>>>,
+    slices: Vec<SliceNode<'gc, 's>>,
+    locals: Vec<NonNull<dyn NodeTrait<'gc> + 'gc>>,
+    scopes: Vec<NonNull<Roots<'gc>>>,
+    stack: Vec<&'gc (dyn NodeTrait<'gc> + 'gc)>,
+    building: Vec<NonNull<dyn NodeTrait<'gc> + 'gc>>,
+    next_id: u64,
+}
+
+impl<'gc, 's> GcInner<'gc, 's> {
+    /// 执行标记清扫，参见 [`Gc::clear`]
+    ///
+    /// # 安全
+    ///
+    /// 调用者需要保证不存在未登记的 [`GcRef`]
+    ///
+    unsafe fn clear(&mut self) {
+        {
+            let inner = self;
+
+            let mut stack = take(&mut inner.stack);
+            stack.reserve(
+                ((inner.nodes.len() + inner.slices.len() + inner.forgets.len()) as f32
+                    * inner.config.stack_factor) as usize,
             );
 
             for &r in inner.nodes.iter() {
@@ -188,6 +854,14 @@ impl<'gc, 's: 'gc> Gc<'gc, 's> {
                     NodeHead::from_node_trait(r.as_ref()).set_marker(Unknown);
                 }
             }
+            for r in inner.slices.iter() {
+                if r.node.as_ref().root() != 0 {
+                    NodeHead::from_node_trait(r.node.as_ref()).set_marker(Trace);
+                    stack.push(r.node.as_ref());
+                } else {
+                    NodeHead::from_node_trait(r.node.as_ref()).set_marker(Unknown);
+                }
+            }
             for &r in inner.forgets.iter() {
                 if r.as_ref().root() != 0 {
                     NodeHead::from_node_trait(r.as_ref().deref()).set_marker(Trace);
@@ -197,6 +871,20 @@ impl<'gc, 's: 'gc> Gc<'gc, 's> {
                 }
             }
 
+            for &r in inner.building.iter() {
+                NodeHead::from_node_trait(r.as_ref()).set_marker(Trace);
+                stack.push(r.as_ref());
+            }
+
+            for &scope in inner.scopes.iter() {
+                for &r in scope.as_ref().borrow().iter() {
+                    if NodeHead::from_node_trait(r.as_ref()).get_marker() == Unknown {
+                        NodeHead::from_node_trait(r.as_ref()).set_marker(Trace);
+                        stack.push(r.as_ref());
+                    }
+                }
+            }
+
             while let Some(r) = stack.pop() {
                 match NodeHead::from_node_trait(r).get_marker() {
                     Trace => {
@@ -251,6 +939,17 @@ impl<'gc, 's: 'gc> Gc<'gc, 's> {
                 swap(&mut inner.nodes, &mut new_nodes);
             }
 
+            inner.slices.retain(|i| {
+                match NodeHead::from_node_trait(i.node.as_ref()).get_marker() {
+                    Unknown => {
+                        (i.drop)(i.node.cast(), i.len);
+                        false
+                    }
+                    Strong => true,
+                    Trace => unreachable!(),
+                }
+            });
+
             let forgets = take(&mut inner.forgets);
             let mut new_forgets = forgets
                 .into_iter()
@@ -264,26 +963,76 @@ impl<'gc, 's: 'gc> Gc<'gc, 's> {
                 .collect::<Vec<_>>();
             new_forgets.reserve(inner.config.forget_cap);
             swap(&mut inner.forgets, &mut new_forgets);
+
+            inner.stack = stack;
         }
     }
-}
-
-struct GcInner<'gc, 's: 'gc> {
-    _marker: PhantomData<*mut &'gc ()>,
-    config: Config,
-    nodes: Vec<NonNull<dyn NodeTrait<'gc> + 's>>,
-    forgets: Vec<NonNull<ManuallyDrop<dyn NodeTrait<'gc> + 's>>>,
-}
-
-impl<'gc, 's> GcInner<'gc, 's> {
-    unsafe fn new(config: Config) -> Self {
-        Self {
+    /// 使用 `storage` 中保留的缓冲区创建
+    ///
+    unsafe fn new(config: Config, storage: &mut GcStorage) -> Self {
+        let mut inner = Self {
             _marker: PhantomData,
             config,
-            nodes: Vec::with_capacity(config.init_cap),
-            forgets: Vec::with_capacity(config.forget_cap),
-        }
+            nodes: recast(take(&mut storage.nodes)),
+            forgets: recast(take(&mut storage.forgets)),
+            slices: recast(take(&mut storage.slices)),
+            locals: recast(take(&mut storage.locals)),
+            scopes: Vec::new(),
+            stack: recast(take(&mut storage.stack)),
+            building: Vec::new(),
+            next_id: 0,
+        };
+        inner.nodes.reserve(config.init_cap);
+        inner.forgets.reserve(config.forget_cap);
+        inner
+    }
+
+    /// 将清空的缓冲区归还给 `storage`
+    ///
+    unsafe fn store(&mut self, storage: &mut GcStorage) {
+        self.locals.clear();
+        self.stack.clear();
+        storage.nodes = recast(take(&mut self.nodes));
+        storage.forgets = recast(take(&mut self.forgets));
+        storage.slices = recast(take(&mut self.slices));
+        storage.locals = recast(take(&mut self.locals));
+        storage.stack = recast(take(&mut self.stack));
+    }
+
+    /// 分配一个新的对象标识
+    ///
+    fn next_id(&mut self) -> NodeId {
+        self.next_id += 1;
+        NodeId::new(self.next_id)
     }
+
+    /// 将对象加入管理，返回的引用没有被根引用
+    ///
+    unsafe fn manage<T: Target>(&mut self, node: Box<Node<'gc, T>>) -> &'gc Node<'gc, T> {
+        let node_ref = transmute::<&'_ Node<'gc, T>, &'gc Node<'gc, T>>(node.deref());
+        self.nodes
+            .push(NonNull::new_unchecked(Box::into_raw(transmute::<
+                Box<dyn NodeTrait<'gc> + 'gc>,
+                Box<dyn NodeTrait<'gc> + 's>,
+            >(node))));
+        node_ref
+    }
+
+    /// 将对象加入遗忘管理，返回的引用没有被根引用
+    ///
+    unsafe fn forget<T: Target>(
+        &mut self,
+        node: Box<ManuallyDrop<Node<'gc, T>>>,
+    ) -> &'gc Node<'gc, T> {
+        let node_ref = transmute::<&'_ Node<'gc, T>, &'gc Node<'gc, T>>(node.deref());
+        self.forgets
+            .push(NonNull::new_unchecked(Box::into_raw(transmute::<
+                Box<ManuallyDrop<dyn NodeTrait<'gc> + 'gc>>,
+                Box<ManuallyDrop<dyn NodeTrait<'gc> + 's>>,
+            >(node))));
+        node_ref
+    }
+
     unsafe fn clear_all(&mut self) {
         if self.config.pre_drop {
             for node in self.nodes.iter() {
@@ -296,11 +1045,25 @@ impl<'gc, 's> GcInner<'gc, 's> {
         for node in self.forgets.iter() {
             drop(Box::from_raw(node.as_ptr()));
         }
+        for node in self.slices.iter() {
+            (node.drop)(node.node.cast(), node.len);
+        }
         self.nodes.clear();
         self.forgets.clear();
+        self.slices.clear();
     }
 }
 
+/// 以切片形式存储的对象
+///
+/// `node` 指向对象的前缀视图，销毁时需要借助长度恢复完整的对象
+///
+struct SliceNode<'gc, 's: 'gc> {
+    node: NonNull<dyn NodeTrait<'gc> + 's>,
+    len: usize,
+    drop: unsafe fn(NonNull<NodeHead>, usize),
+}
+
 impl<'gc, 's> Debug for GcInner<'gc, 's> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut s = f.debug_struct("GcInner");
@@ -338,6 +1101,7 @@ impl<'gc, 's> Debug for GcInner<'gc, 's> {
             }
         }
         s.field("forgets", &M(&self.forgets));
+        s.field("slices", &self.slices.len());
         s.finish()
     }
 }
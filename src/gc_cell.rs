@@ -0,0 +1,119 @@
+use crate::gc::NoGcGuard;
+use crate::node::Node;
+use crate::target::Target;
+use std::cell::{BorrowError, BorrowMutError, Ref, RefCell, RefMut};
+use std::fmt::{Debug, Formatter};
+
+/// 具有内部可变性的被管理值包装
+///
+/// 可变借用通过被管理的对象进行，需要持有同一个 GC 的 [`NoGcGuard`]，因此可变借用存在期间不会发生回收
+///
+/// [`Target::pre_drop`] 会被转发给内部的值，此时值不会被可变借用
+///
+pub struct GcCell<T: ?Sized> {
+    cell: RefCell<T>,
+}
+
+impl<T> GcCell<T> {
+    /// 包装一个值
+    ///
+    pub fn new(value: T) -> Self {
+        Self {
+            cell: RefCell::new(value),
+        }
+    }
+
+    /// 取出包装的值
+    ///
+    pub fn into_inner(self) -> T {
+        self.cell.into_inner()
+    }
+}
+
+impl<T: ?Sized> GcCell<T> {
+    /// 不可变借用
+    ///
+    /// # 恐慌
+    ///
+    /// 如果值正在被可变借用则恐慌
+    ///
+    #[inline(always)]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.cell.borrow()
+    }
+
+    /// 尝试不可变借用，如果值正在被可变借用则返回 [`Err`]
+    ///
+    #[inline(always)]
+    pub fn try_borrow(&self) -> Result<Ref<'_, T>, BorrowError> {
+        self.cell.try_borrow()
+    }
+
+    /// 获取可变引用，静态保证没有其他借用
+    ///
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.cell.get_mut()
+    }
+}
+
+impl<'gc, T: ?Sized + Target> Node<'gc, GcCell<T>> {
+    /// 可变借用，借用期间由同一个 GC 的 `guard` 保证不会发生回收
+    ///
+    /// # 恐慌
+    ///
+    /// 如果值正在被借用则恐慌
+    ///
+    /// 其他 GC 的守卫无法阻止回收，因此不被接受
+    ///
+    /// ```compile_fail
+    /// use scope_gc::gc::scope_gc;
+    /// use scope_gc::gc_cell::GcCell;
+    /// use scope_gc::raw_type::RawType;
+    ///
+    /// scope_gc(Default::default(), |gc| {
+    ///     let x = gc.new(GcCell::new(RawType(1)));
+    ///     gc.sub_scope(|mut sub| {
+    ///         let guard = sub.no_gc();
+    ///         x.borrow_mut(&guard).0 = 2;
+    ///     });
+    /// });
+    /// ```
+    ///
+    #[inline(always)]
+    pub fn borrow_mut<'r>(&'r self, _guard: &'r NoGcGuard<'_, 'gc, '_>) -> RefMut<'r, T> {
+        self.value.cell.borrow_mut()
+    }
+
+    /// 尝试可变借用，如果值正在被借用则返回 [`Err`]，参见 [`Node::borrow_mut`]
+    ///
+    #[inline(always)]
+    pub fn try_borrow_mut<'r>(
+        &'r self,
+        _guard: &'r NoGcGuard<'_, 'gc, '_>,
+    ) -> Result<RefMut<'r, T>, BorrowMutError> {
+        self.value.cell.try_borrow_mut()
+    }
+}
+
+impl<T: ?Sized + Target> Target for GcCell<T> {
+    type RefObject<'gc> = T::RefObject<'gc>;
+
+    #[inline(always)]
+    /// # 恐慌
+    ///
+    /// 可变借用期间无法回收，只有泄漏的借用会使值在此时仍被借用，此时恐慌
+    ///
+    unsafe fn pre_drop<'gc>(&self, ref_set: &Self::RefObject<'gc>) {
+        self.cell
+            .try_borrow_unguarded()
+            .expect("GcCell is still borrowed during collection")
+            .pre_drop(ref_set);
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for GcCell<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("GcCell").field(&&self.cell).finish()
+    }
+}
@@ -0,0 +1,130 @@
+use crate::node::{Node, NodeTrait};
+use crate::target::{RefSet, Target};
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::mem::transmute;
+
+/// 被管理的闭包
+///
+/// 闭包捕获的被管理对象保存在引用集合 `S` 中，因此会被追踪而不是作为根引用
+///
+/// 只能通过 [`crate::gc::Gc::new_fn`] 创建，此时 `'gc` 与所属 GC 的生命周期标识一致
+///
+pub struct GcFn<'gc, S: RefSet<'gc>, F> {
+    _marker: PhantomData<*mut &'gc ()>,
+    _env: PhantomData<fn() -> S>,
+    f: F,
+}
+
+impl<'gc, S: RefSet<'gc>, F> GcFn<'gc, S, F> {
+    pub(crate) fn new(f: F) -> Self {
+        Self {
+            _marker: PhantomData,
+            _env: PhantomData,
+            f,
+        }
+    }
+}
+
+impl<'l, S: RefSet<'l>, F> Target for GcFn<'l, S, F> {
+    type RefObject<'gc> = FnEnv<'l, S>;
+}
+
+impl<'gc, S: RefSet<'gc>, F> Debug for GcFn<'gc, S, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcFn").finish()
+    }
+}
+
+/// [`GcFn`] 的引用集合
+///
+/// 内部的 `S` 只能在 [`crate::gc::Gc::new_fn`] 的 `init` 和被管理的闭包中访问，此时 `'l` 就是对象所属 GC 的 `'gc`
+///
+/// 其他类型即使将它作为引用集合并放入另一个 GC，也无法向其中写入引用
+///
+/// ```compile_fail
+/// use scope_gc::gc::{scope_gc, Gc};
+/// use scope_gc::gc_fn::FnEnv;
+/// use scope_gc::node::Node;
+/// use scope_gc::strong_ref::StrongRef;
+/// use scope_gc::target::Target;
+/// use std::marker::PhantomData;
+///
+/// struct X;
+///
+/// impl Target for X {
+///     type RefObject<'gc> = ();
+/// }
+///
+/// struct Evil<'l>(PhantomData<&'l ()>);
+///
+/// impl<'l> Target for Evil<'l> {
+///     type RefObject<'gc> = FnEnv<'l, StrongRef<'l, Node<'l, X>>>;
+/// }
+///
+/// scope_gc(Default::default(), |a: Gc| {
+///     let x = a.new(X);
+///     scope_gc(Default::default(), |b: Gc| {
+///         let e = b.forget(Evil(PhantomData));
+///         e.ref_set().set_ref(&x);
+///     });
+/// });
+/// ```
+///
+pub struct FnEnv<'l, S: RefSet<'l>> {
+    _marker: PhantomData<*mut &'l ()>,
+    env: S,
+}
+
+impl<'l, S: RefSet<'l>> FnEnv<'l, S> {
+    /// 获取闭包的引用集合
+    ///
+    #[inline(always)]
+    pub(crate) fn env(&self) -> &S {
+        &self.env
+    }
+}
+
+impl<'l, S: RefSet<'l>> Debug for FnEnv<'l, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.env.fmt(f)
+    }
+}
+
+unsafe impl<'gc, 'l, S: RefSet<'l>> RefSet<'gc> for FnEnv<'l, S> {
+    #[inline(always)]
+    unsafe fn build() -> Self {
+        Self {
+            _marker: PhantomData,
+            env: S::build(),
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn collect(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+        // # 安全
+        //
+        // `env` 只能经由 [`crate::gc::Gc::new_fn`] 写入，此时 `'l` 就是 `'gc`
+        // 在其他 GC 中构造的 [`FnEnv`] 始终为空
+        //
+        self.env.collect(transmute::<
+            &mut Vec<&dyn NodeTrait<'gc>>,
+            &mut Vec<&dyn NodeTrait<'l>>,
+        >(stack))
+    }
+}
+
+/// 可以被调用的对象，用于擦除 [`GcFn`] 的具体类型
+///
+pub trait NodeFn<'gc, A, R>: NodeTrait<'gc> {
+    fn call(&self, args: A) -> R;
+}
+
+impl<'gc, S: RefSet<'gc>, F: Fn(&S, A) -> R, A, R> NodeFn<'gc, A, R>
+    for Node<'gc, GcFn<'gc, S, F>>
+{
+    #[inline(always)]
+    fn call(&self, args: A) -> R {
+        (self.value.f)(&self.ref_set.env, args)
+    }
+}
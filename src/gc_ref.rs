@@ -0,0 +1,60 @@
+use crate::node::NodeTrait;
+use crate::root_ref::RootRef;
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+
+/// 不增加根引用计数的借用引用
+///
+/// 只能在保证期间不会发生回收时获得，参见 [`crate::gc::NoGcGuard`]
+///
+pub struct GcRef<'r, T: ?Sized> {
+    r: &'r T,
+}
+
+impl<'r, T: ?Sized> GcRef<'r, T> {
+    #[inline(always)]
+    pub(crate) fn new(r: &'r T) -> Self {
+        Self { r }
+    }
+
+    /// 获取底层借用
+    ///
+    #[inline(always)]
+    pub fn get(self) -> &'r T {
+        self.r
+    }
+
+    /// 提升为根引用，使其可以在借用结束后继续存活
+    ///
+    #[inline(always)]
+    pub fn root<'gc>(self) -> RootRef<'gc, T>
+    where
+        T: NodeTrait<'gc> + 'gc,
+    {
+        RootRef::new(self.r)
+    }
+}
+
+impl<'r, T: ?Sized> Copy for GcRef<'r, T> {}
+
+impl<'r, T: ?Sized> Clone for GcRef<'r, T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'r, T: ?Sized + Debug> Debug for GcRef<'r, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("GcRef").field(&self.r).finish()
+    }
+}
+
+impl<'r, T: ?Sized> Deref for GcRef<'r, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        self.r
+    }
+}
@@ -18,25 +18,69 @@
 //! `GC::new` 只能接受存活时间长于闭包的值
 //! 使用 `GC::forget` 接受存活时间较短的值，但执行回收时仅仅回收内存，其预析构和析构函数均不会被调用  
 //! 使用 `unsafe GC::dangling` 接受存活时间较短的值，且执行与 `GC::new` 同样的逻辑  
+//! 使用 `GC::new_short_lived` 接受存活时间较短的值，该值必须实现 `DangleSafe`，即析构时不会访问借用的数据  
 //! 使用 `GC::new_raw(x)` 其等价于 `GC::new(RawType(x))`，不过不推荐如此，在此情况下，使用来自 `Rc` 无疑是更好的选择
 //!
+//! 启用 `derive` 特性后，可以使用 `#[derive(RefSet)]` 为字段全部是引用集合的结构体和枚举实现 `RefSet` 和 `Debug`
+//! 使用 `#[gc_target(refs = ..., node_trait = NodeA)]` 生成 `Target` 实现和节点特征
+//!
+//! 在异步代码中使用 `scope_gc_async`，GC 会在异步块执行期间保持存活
+//!
+//! 需要在多个线程间共享时使用 `scope_gc_sync`，每个线程通过 `SyncGc::mutator` 注册后才能访问对象，`SyncGc::clear` 会在所有线程到达安全点后回收
+//!
+//! 如果希望值自身持有引用，可以为值实现 `scope_gc::trace::Trace`，在值中使用 `TraceRef`，并通过 `GC::new_traced` 托管
+//!
+//! 使用 `GC::new_slice_copy`、`GC::new_str` 和 `GC::new_from_iter` 托管切片和字符串，使用 `GC::new_dyn` 托管特征对象，值都直接存储在对象内部
+//!
 
+// 安全要求统一写在中文的 `# 安全` 小节中
+#![allow(clippy::missing_safety_doc)]
 #![cfg_attr(feature = "_unsize", feature(unsize))]
 #![cfg_attr(feature = "_coerce_unsized", feature(coerce_unsized))]
 
+pub mod dangle_safe;
+pub mod dyn_ref_set;
 pub mod gc;
+pub mod gc_cell;
+pub mod gc_fn;
+pub mod gc_ref;
+pub mod mapped_root_ref;
 pub mod node;
+pub mod once_ref;
 pub mod raw_type;
 pub mod root_ref;
+pub mod short_root_ref;
 pub mod strong_ref;
 pub mod strong_vec;
 pub mod struct_ref;
+pub mod sync;
 pub mod target;
+pub mod trace;
+
+/// 派生 [`target::RefSet`]，需要启用 `derive` 特性
+///
+#[cfg(feature = "derive")]
+pub use scope_gc_derive::RefSet;
+
+/// 生成 [`target::Target`] 实现和节点特征，需要启用 `derive` 特性
+///
+#[cfg(feature = "derive")]
+pub use scope_gc_derive::gc_target;
+
+/// 派生 [`dangle_safe::DangleSafe`]，需要启用 `derive` 特性
+///
+#[cfg(feature = "derive")]
+pub use scope_gc_derive::DangleSafe;
 
 #[cfg(test)]
 mod tests {
+    use crate::dyn_ref_set::DynRefSet;
     use crate::gc::{scope_gc, Config, Gc};
-    use crate::node::{Node, NodeTrait};
+    use crate::gc_cell::GcCell;
+    use crate::gc_fn::NodeFn;
+    use crate::node::{CyclicNode, Node, NodeTrait};
+    use crate::once_ref::OnceRef;
+    use crate::root_ref::RootRef;
     use crate::strong_ref::StrongRef;
     use crate::target::Target;
     use std::ops::Deref;
@@ -76,7 +120,7 @@ mod tests {
             ..Default::default()
         };
         let i = 1;
-        scope_gc(config, |gc: Gc| {
+        scope_gc(config, |mut gc: Gc| {
             let x = gc.new(A(&i));
             let y = gc.new(A(&i));
             x.ref_set().set_ref(y.deref());
@@ -100,4 +144,940 @@ mod tests {
             println!("{:#?}", gc);
         });
     }
+
+    #[test]
+    fn new_with() {
+        let i = 1;
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new(A(&i));
+            let y = gc.root_scope(|scope| {
+                scope.gc().new_with(A(&i), |r| {
+                    assert!(r.get().is_none());
+                    r.set_ref(x.deref());
+                    scope.clear();
+                })
+            });
+            assert_eq!(y.ref_set().get().unwrap().inner(), &1);
+            drop(x);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            drop(y);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+        });
+    }
+
+    struct B;
+
+    impl Target for B {
+        type RefObject<'gc> = OnceRef<'gc, Node<'gc, B>>;
+    }
+
+    #[test]
+    fn once_ref() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new(B);
+            let y = gc.new(B);
+            assert!(!x.ref_set().is_init());
+            assert!(x.ref_set().try_get().is_none());
+            assert!(x.ref_set().init(&y).is_ok());
+            assert!(x.ref_set().init(&x).is_err());
+            drop(y);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            let _y = x.ref_set().get();
+            drop(x);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 1);
+        });
+    }
+
+    struct C(i32);
+
+    impl Target for C {
+        type RefObject<'gc> = StrongRef<'gc, Node<'gc, C>>;
+    }
+
+    #[test]
+    fn new_cyclic() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new_cyclic(|this: &CyclicNode<C>| {
+                this.link(|this| this.ref_set().set_ref(this));
+                C(1)
+            });
+            assert_eq!(x.ref_set().get().unwrap().0, 1);
+            drop(x);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+
+            let child = gc.new(C(2));
+            let x = gc.new_cyclic(|this: &CyclicNode<C>| {
+                this.ref_set().set_ref(&child);
+                this.link(move |this| child.ref_set().set_ref(this));
+                C(1)
+            });
+            let child = x.ref_set().get().unwrap();
+            assert!(RootRef::ptr_eq(&child.ref_set().get().unwrap(), &x));
+            drop(child);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+
+            let count = std::rc::Rc::new(());
+            let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                gc.new_cyclic(|this: &CyclicNode<C>| {
+                    let count = count.clone();
+                    this.link(move |_| drop(count));
+                    this.ref_set().set_ref(&x);
+                    panic!();
+                })
+            }));
+            assert!(r.is_err());
+            assert_eq!(std::rc::Rc::strong_count(&count), 1);
+            assert_eq!(gc.get_node_count(), 2);
+
+            struct Reader<'gc>(
+                RootRef<'gc, Node<'gc, C>>,
+                std::rc::Rc<std::cell::Cell<i32>>,
+            );
+
+            impl<'gc> Drop for Reader<'gc> {
+                fn drop(&mut self) {
+                    self.1.set(self.0.ref_set().get().unwrap().0);
+                }
+            }
+
+            let read = std::rc::Rc::new(std::cell::Cell::new(0));
+            let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                gc.new_cyclic(|this: &CyclicNode<C>| {
+                    this.ref_set().set_ref(&x);
+                    let reader = Reader(x.clone(), read.clone());
+                    this.link(move |_| drop(reader));
+                    panic!();
+                })
+            }));
+            assert!(r.is_err());
+            assert_eq!(read.get(), 2);
+            assert_eq!(gc.get_node_count(), 2);
+            drop(x);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+
+            let x = gc.root_scope(|scope| {
+                scope.gc().new_cyclic(|this: &CyclicNode<C>| {
+                    this.ref_set().set_ref(&scope.gc().new(C(2)));
+                    scope.clear();
+                    scope.clear();
+                    assert_eq!(this.ref_set().get().unwrap().0, 2);
+                    C(1)
+                })
+            });
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            drop(x);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+        });
+    }
+
+    #[test]
+    fn strong_ref_ops() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new(C(1));
+            let y = gc.new(C(2));
+            let z = gc.new(C(3));
+            assert!(x.ref_set().is_none());
+            assert!(x.ref_set().replace(&y).is_none());
+            assert!(x.ref_set().is_some() && x.ref_set().ptr_eq(&y));
+            assert_eq!(x.ref_set().replace(&z).unwrap().0, 2);
+            x.ref_set().swap(y.ref_set());
+            assert!(x.ref_set().is_none());
+            {
+                let guard = gc.no_gc();
+                assert_eq!(y.ref_set().with(&guard, |r| r.map(|r| r.0)), Some(3));
+            }
+            assert_eq!(y.ref_set().take().unwrap().0, 3);
+            assert!(y.ref_set().is_none());
+        });
+    }
+
+    #[test]
+    fn strong_ref_identity() {
+        use std::hash::{BuildHasher, RandomState};
+
+        scope_gc(Default::default(), |gc: Gc| {
+            let x = gc.new(C(1));
+            let y = gc.new(C(2));
+            let z = gc.new(C(3));
+            assert_eq!(x.ref_set(), y.ref_set());
+            x.ref_set().set_ref(&z);
+            assert_ne!(x.ref_set(), y.ref_set());
+            assert!(y.ref_set() < x.ref_set());
+            y.ref_set().set_ref(&z);
+            assert_eq!(x.ref_set(), y.ref_set());
+            let s = RandomState::new();
+            assert_eq!(s.hash_one(x.ref_set()), s.hash_one(y.ref_set()));
+            y.ref_set().set_ref(&x);
+            assert!(y.ref_set() < x.ref_set());
+        });
+    }
+
+    #[test]
+    fn no_gc() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new(C(1));
+            let y = gc.new(C(2));
+            x.ref_set().set_ref(&y);
+            drop(y);
+            let y = {
+                let guard = gc.no_gc();
+                let y = x.ref_set().get_ref(&guard).unwrap();
+                assert_eq!(y.0, 2);
+                y.root()
+            };
+            x.ref_set().set_none();
+            drop(x);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 1);
+            assert_eq!(y.0, 2);
+        });
+    }
+
+    #[test]
+    fn root_scope() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let y = gc.root_scope(|scope| {
+                let x = scope.new(C(1));
+                let y = scope.new(C(2));
+                x.ref_set().set_ref(&y);
+                scope.clear();
+                assert_eq!(scope.gc().get_node_count(), 2);
+                x.root()
+            });
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            drop(y);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+        });
+    }
+
+    #[test]
+    fn downcast() {
+        scope_gc(Default::default(), |gc: Gc| {
+            let x = gc.new(C(1));
+            let d = x.clone().coerce::<dyn NodeTrait, _>(|n| n);
+            assert!(d.is::<C>());
+            assert!(!d.is::<B>());
+            assert!(unsafe { d.downcast_ref::<B>() }.is_none());
+            let d = unsafe { d.downcast::<B>() }.unwrap_err();
+            let c = unsafe { d.downcast::<C>() }.unwrap();
+            assert_eq!(c.0, 1);
+            assert_eq!(x.root(), 2);
+        });
+    }
+
+    #[test]
+    fn coerce() {
+        let i = 1;
+        scope_gc(Default::default(), |gc: Gc| {
+            let x = gc.new(A(&i));
+            let y: RootRef<dyn NodeA> = x.clone().coerce(|n| n as _);
+            assert_eq!(*y.inner(), 1);
+            assert_eq!(y.root(), 2);
+            drop(x);
+            assert_eq!(y.root(), 1);
+        });
+    }
+
+    #[test]
+    fn identity() {
+        scope_gc(Default::default(), |gc: Gc| {
+            let x = gc.new(C(1));
+            let y = gc.new(C(2));
+            assert!(x.id() < y.id());
+            assert_eq!(x, x.clone());
+            assert_ne!(x, y);
+            assert!(x < y);
+            let set = [x.clone(), y.clone(), x.clone()]
+                .into_iter()
+                .collect::<std::collections::HashSet<_>>();
+            assert_eq!(set.len(), 2);
+            x.ref_set().set_ref(&y);
+            assert_eq!(x.ref_set().id(), Some(y.id()));
+            assert!(x.ref_set().ptr_eq(&y));
+        });
+    }
+
+    #[test]
+    fn map() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new(C(1));
+            let v = RootRef::map(x.clone(), |n| &n.0);
+            let w = v.clone();
+            drop(x);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 1);
+            assert_eq!(*v, 1);
+            drop(v);
+            assert_eq!(*w, 1);
+            drop(w);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+        });
+    }
+
+    #[test]
+    fn nested_root_scope() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            gc.root_scope(|outer| {
+                let x = outer.new(C(1));
+                let y = outer.root_scope(|inner| {
+                    let y = outer.new(C(2));
+                    let z = inner.new(C(3));
+                    y.ref_set().set_ref(&z);
+                    inner.clear();
+                    assert_eq!(inner.gc().get_node_count(), 3);
+                    y
+                });
+                outer.clear();
+                assert_eq!(outer.gc().get_node_count(), 3);
+                y.ref_set().set_none();
+                outer.clear();
+                assert_eq!(outer.gc().get_node_count(), 2);
+                assert_eq!(x.0 + y.0, 3);
+            });
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+        });
+    }
+
+    #[test]
+    fn gc_cell() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new(GcCell::new(C(1)));
+            {
+                let guard = gc.no_gc();
+                x.borrow_mut(&guard).0 = 2;
+            }
+            assert_eq!(x.borrow().0, 2);
+            drop(x);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+        });
+    }
+
+    struct D;
+
+    impl Target for D {
+        type RefObject<'gc> = DynRefSet<'gc>;
+    }
+
+    #[test]
+    #[should_panic(expected = "GcCell is still borrowed during collection")]
+    fn gc_cell_leaked_borrow() {
+        let config = Config {
+            pre_drop: true,
+            ..Default::default()
+        };
+        scope_gc(config, |mut gc: Gc| {
+            let x = gc.new(GcCell::new(C(1)));
+            {
+                let guard = gc.no_gc();
+                std::mem::forget(x.borrow_mut(&guard));
+            }
+            drop(x);
+            gc.clear();
+        });
+    }
+
+    #[test]
+    fn dyn_ref_set() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new(D);
+            let y = gc.new(C(1));
+            assert_eq!(x.ref_set().push(&*y), 0);
+            assert_eq!(x.ref_set().set_named("self", &*x), 1);
+            assert_eq!(x.ref_set().set_named("c", &*y), 2);
+            assert_eq!(x.ref_set().set_named("self", &*x), 1);
+            drop(y);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            let y = x.ref_set().get_named("c").unwrap();
+            assert_eq!(unsafe { y.downcast_ref::<C>() }.unwrap().0, 1);
+            assert!(x.ref_set().remove(0).is_some());
+            assert!(x.ref_set().remove(2).is_none());
+            assert!(x.ref_set().set(0, &*y).unwrap().is::<D>());
+            assert!(x.ref_set().set(0, &*x).unwrap().is::<C>());
+            assert_eq!(x.ref_set().index_of("c"), Some(1));
+            drop(y);
+            x.ref_set().remove_named("c");
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 1);
+        });
+    }
+
+    #[test]
+    fn gc_fn() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new(C(1));
+            let f = gc.new_fn(
+                |env: &StrongRef<Node<C>>| env.set_ref(&x),
+                |env, y: i32| env.get().unwrap().0 + y,
+            );
+            drop(x);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            let g: RootRef<dyn NodeFn<i32, i32>> = f.coerce(|n| n as _);
+            assert_eq!(g.call(2), 3);
+            drop(g);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+        });
+    }
+
+    #[test]
+    fn unsized_value() {
+        use std::cell::Cell;
+        use std::fmt::Debug;
+        use std::rc::Rc;
+
+        struct Counter(Rc<Cell<i32>>);
+
+        impl Drop for Counter {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let count = Rc::new(Cell::new(0));
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new_slice_copy(&[1, 2, 3]);
+            let s = gc.new_str("hello");
+            let v = gc.new_from_iter((0..4).map(|_| Counter(count.clone())));
+            let d = gc.new_dyn(42_u64, |v| v as &dyn Debug);
+            assert_eq!(&x.value.0, &[1, 2, 3]);
+            assert_eq!(&s.value.0, "hello");
+            assert_eq!(v.value.0.len(), 4);
+            assert_eq!(format!("{:?}", &d.value.0), "42");
+            assert_eq!(gc.get_node_count(), 4);
+
+            let o = gc.new(D);
+            o.ref_set().push(&*s);
+            drop(s);
+            drop(v);
+            gc.clear();
+            assert_eq!(count.get(), 4);
+            assert_eq!(gc.get_node_count(), 4);
+            drop(o);
+            drop(d);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 1);
+            let _ = gc.new_from_iter((0..2).map(|_| Counter(count.clone())));
+        });
+        assert_eq!(count.get(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "coerce must return the given reference")]
+    fn new_dyn_field() {
+        use std::fmt::Debug;
+
+        #[repr(C, align(16))]
+        struct Aligned(u8);
+
+        scope_gc(Default::default(), |gc: Gc| {
+            gc.new_dyn(Aligned(1), |v| &v.0 as &dyn Debug);
+        });
+    }
+
+    #[test]
+    fn new_many() {
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let xs = gc.new_many((0..100).map(C));
+            assert_eq!(xs.len(), 100);
+            assert!(xs.iter().enumerate().all(|(i, x)| x.0 == i as i32));
+            xs[0].ref_set().set_ref(&xs[1]);
+            let fs = gc.forget_many((0..10).map(C));
+            assert_eq!(gc.get_forget_count(), 10);
+            let x = xs[0].clone();
+            drop(xs);
+            drop(fs);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            assert_eq!(gc.get_forget_count(), 0);
+            assert_eq!(x.ref_set().get().unwrap().0, 1);
+        });
+    }
+
+    #[test]
+    fn traced() {
+        use crate::trace::{Trace, TraceRef, Traced};
+
+        struct Items;
+
+        impl Traced for Items {
+            type Value<'gc> = Vec<Item<'gc>>;
+        }
+
+        struct Item<'gc> {
+            value: i32,
+            next: TraceRef<'gc, Node<'gc, C>>,
+        }
+
+        unsafe impl<'gc> Trace<'gc> for Item<'gc> {
+            unsafe fn trace(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+                self.next.trace(stack);
+            }
+
+            unsafe fn adopt(&self) {
+                self.next.adopt();
+            }
+
+            unsafe fn release(&self) {
+                self.next.release();
+            }
+        }
+
+        scope_gc(Default::default(), |mut gc: Gc| {
+            let x = gc.new(C(1));
+            let y = gc.new(C(2));
+            let items = vec![
+                Item {
+                    value: 10,
+                    next: TraceRef::new(Some(&x)),
+                },
+                Item {
+                    value: 20,
+                    next: TraceRef::new(Some(&y)),
+                },
+            ];
+            drop(x);
+            drop(y);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            assert!(!items[0].next.is_managed());
+
+            let list = gc.new_traced::<Items>(items);
+            assert!(list.traced()[0].next.is_managed());
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 3);
+            assert_eq!(list.traced()[1].value, 20);
+            assert_eq!(list.traced()[1].next.get().unwrap().0, 2);
+            list.traced()[1].next.set(None);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+
+            let z = gc.new(C(3));
+            let item = {
+                let guard = gc.no_gc();
+                let mut items = list.traced_mut(&guard);
+                assert!(!items[0].next.is_managed());
+                items.push(Item {
+                    value: 30,
+                    next: TraceRef::new(Some(&z)),
+                });
+                items.remove(0)
+            };
+            drop(z);
+            assert!(list.traced()[1].next.is_managed());
+            assert!(!item.next.is_managed());
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 3);
+            assert_eq!(item.next.get().unwrap().0, 1);
+            drop(item);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            drop(list);
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 0);
+        });
+    }
+
+    #[test]
+    fn storage() {
+        use crate::gc::{scope_gc_with, GcStorage};
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut storage = GcStorage::new();
+        for i in 0..3 {
+            let n = scope_gc_with(&mut storage, Default::default(), |mut gc: Gc| {
+                for j in 0..100 {
+                    gc.new(C(i * j));
+                }
+                gc.clear();
+                gc.get_node_count()
+            });
+            assert_eq!(n, 0);
+        }
+        assert!(format!("{:?}", storage).contains("nodes: "));
+
+        let rc = std::rc::Rc::new(());
+        let r = catch_unwind(AssertUnwindSafe(|| {
+            scope_gc_with(&mut storage, Default::default(), |gc: Gc| {
+                let _x = gc.new_raw(rc.clone());
+                panic!("unwind");
+            })
+        }));
+        assert!(r.is_err());
+        assert_eq!(std::rc::Rc::strong_count(&rc), 1);
+        let n = scope_gc_with(&mut storage, Default::default(), |gc: Gc| {
+            gc.get_node_count()
+        });
+        assert_eq!(n, 0);
+    }
+
+    #[test]
+    fn sub_scope() {
+        use std::cell::Cell;
+
+        struct Local<'l>(&'l Cell<i32>);
+
+        impl<'l> Target for Local<'l> {
+            type RefObject<'gc> = StrongRef<'gc, dyn NodeTrait<'gc>>;
+
+            unsafe fn pre_drop<'gc>(&self, _ref_set: &Self::RefObject<'gc>) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        impl<'l> Drop for Local<'l> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 10);
+            }
+        }
+
+        scope_gc(
+            Config {
+                pre_drop: true,
+                ..Default::default()
+            },
+            |gc: Gc| {
+                let outer = gc.new(C(1));
+                let count = Cell::new(0);
+                let n = gc.sub_scope(|mut sub| {
+                    let x = sub.new(Local(&count));
+                    let y = sub.new(Local(&count));
+                    x.ref_set().set_ref(&*y);
+                    y.ref_set().set_ref(&*x);
+                    drop(y);
+                    sub.clear();
+                    assert_eq!(count.get(), 0);
+                    let _z = sub.new(Local(&count));
+                    outer.0 + sub.get_node_count() as i32
+                });
+                assert_eq!(n, 4);
+                assert_eq!(count.get(), 33);
+                assert_eq!(gc.get_node_count(), 1);
+            },
+        );
+
+        scope_gc(Default::default(), |gc: Gc| {
+            let count = Cell::new(0);
+            gc.sub_scope(|mut sub| {
+                let x = sub.new(Local(&count));
+                x.ref_set().set_ref(&*x);
+                drop(x);
+                sub.clear();
+                assert_eq!(count.get(), 11);
+                let _y = sub.new(Local(&count));
+            });
+            assert_eq!(count.get(), 22);
+        });
+    }
+
+    #[test]
+    fn ambient() {
+        use crate::gc::with_current_gc;
+
+        fn helper() -> Option<usize> {
+            with_current_gc(|gc| {
+                let x = gc.new(C(1));
+                x.ref_set().set_ref(&x);
+                gc.get_node_count()
+            })
+        }
+
+        assert_eq!(helper(), None);
+        scope_gc(Default::default(), |_gc: Gc| {
+            assert_eq!(helper(), None);
+        });
+        let config = Config {
+            ambient: true,
+            ..Default::default()
+        };
+        scope_gc(config, |mut gc: Gc| {
+            let _x = gc.new(C(0));
+            assert_eq!(helper(), Some(2));
+            gc.sub_scope(|sub| {
+                assert_eq!(helper(), Some(3));
+                assert_eq!(sub.get_node_count(), 0);
+            });
+            assert_eq!(helper(), Some(4));
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 1);
+        });
+        assert_eq!(helper(), None);
+
+        struct Probe<'a>(&'a std::cell::Cell<Option<Option<usize>>>);
+
+        impl<'a> Target for Probe<'a> {
+            type RefObject<'gc> = ();
+
+            unsafe fn pre_drop<'gc>(&self, _ref_set: &Self::RefObject<'gc>) {
+                self.0.set(Some(helper()));
+            }
+        }
+
+        let probe = std::cell::Cell::new(None);
+        let config = Config {
+            pre_drop: true,
+            ambient: true,
+            ..Default::default()
+        };
+        scope_gc(config, |mut gc: Gc| {
+            gc.new(Probe(&probe));
+            gc.clear();
+            assert_eq!(probe.get(), Some(None));
+            assert_eq!(helper(), Some(1));
+        });
+    }
+
+    #[test]
+    fn scope_async() {
+        use crate::gc::scope_gc_async;
+        use std::future::Future;
+        use std::pin::pin;
+        use std::rc::Rc;
+        use std::task::{Context, Poll, Waker};
+
+        struct YieldNow(bool);
+
+        impl Future for YieldNow {
+            type Output = ();
+
+            fn poll(mut self: std::pin::Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+                if self.0 {
+                    Poll::Ready(())
+                } else {
+                    self.0 = true;
+                    Poll::Pending
+                }
+            }
+        }
+
+        fn block_on<F: Future>(f: F) -> F::Output {
+            let mut f = pin!(f);
+            let mut cx = Context::from_waker(Waker::noop());
+            loop {
+                if let Poll::Ready(r) = f.as_mut().poll(&mut cx) {
+                    return r;
+                }
+            }
+        }
+
+        let r = block_on(scope_gc_async(Default::default(), async |mut gc: Gc| {
+            let x = gc.new(C(1));
+            let y = gc.new(C(2));
+            x.ref_set().set_ref(&y);
+            drop(y);
+            YieldNow(false).await;
+            gc.clear();
+            assert_eq!(gc.get_node_count(), 2);
+            YieldNow(false).await;
+            x.ref_set().get().unwrap().0
+        }));
+        assert_eq!(r, 2);
+
+        let rc = Rc::new(());
+        {
+            let mut cx = Context::from_waker(Waker::noop());
+            let mut f = Box::pin(scope_gc_async(Default::default(), async |gc: Gc| {
+                let _x = gc.new_raw(rc.clone());
+                YieldNow(false).await;
+            }));
+            assert!(f.as_mut().poll(&mut cx).is_pending());
+            assert_eq!(Rc::strong_count(&rc), 2);
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn sync_gc() {
+        use crate::sync::{
+            scope_gc_sync, SyncGc, SyncNode, SyncRootRef, SyncStrongVec, SyncTarget,
+        };
+
+        struct S(usize);
+
+        impl SyncTarget for S {
+            type RefObject<'gc> = SyncStrongVec<'gc, SyncNode<'gc, S>>;
+        }
+
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SyncGc>();
+        assert_send_sync::<SyncRootRef<SyncNode<S>>>();
+
+        scope_gc_sync(Default::default(), |gc: SyncGc| {
+            let mut m = gc.mutator();
+            let root = gc.new(&m, S(0));
+            m.blocking(|| {
+                std::thread::scope(|scope| {
+                    for t in 0..4 {
+                        let root = &root;
+                        scope.spawn(move || {
+                            let m = gc.mutator();
+                            for i in 0..100 {
+                                let x = gc.new(&m, S(t * 100 + i));
+                                if i % 2 == 0 {
+                                    root.ref_set().push(&m, &x);
+                                }
+                                if i % 10 == 0 {
+                                    gc.clear(&m);
+                                }
+                                m.safepoint();
+                            }
+                        });
+                    }
+                });
+            });
+            gc.clear(&m);
+            assert_eq!(gc.get_node_count(), 1 + 4 * 50);
+            assert_eq!(root.ref_set().len(), 200);
+            let x = root.ref_set().get(&m, 0).unwrap();
+            assert_eq!(x.value().0 % 2, 0);
+            root.ref_set().clear(&m);
+            drop(x);
+            gc.clear(&m);
+            assert_eq!(gc.get_node_count(), 1);
+        });
+    }
+
+    #[test]
+    fn concurrent_mark() {
+        use crate::sync::{scope_gc_sync, SyncConfig, SyncGc, SyncNode, SyncStrongVec, SyncTarget};
+
+        struct S(usize);
+
+        impl SyncTarget for S {
+            type RefObject<'gc> = SyncStrongVec<'gc, SyncNode<'gc, S>>;
+        }
+
+        let config = SyncConfig::default().with_concurrent_mark(true);
+        scope_gc_sync(config, |gc: SyncGc| {
+            let mut m = gc.mutator();
+            let root = gc.new(&m, S(0));
+            m.blocking(|| {
+                std::thread::scope(|scope| {
+                    for t in 0..4 {
+                        let root = &root;
+                        scope.spawn(move || {
+                            let m = gc.mutator();
+                            let local = gc.new(&m, S(0));
+                            for i in 0..100 {
+                                let x = gc.new(&m, S(t * 100 + i));
+                                local.ref_set().push(&m, &x);
+                                drop(x);
+                                if i % 2 == 1 {
+                                    assert_eq!(local.ref_set().pop(&m).unwrap().value().0 % 2, 1);
+                                }
+                                if i % 10 == 0 {
+                                    gc.clear(&m);
+                                }
+                                m.safepoint();
+                            }
+                            while let Some(x) = local.ref_set().pop(&m) {
+                                root.ref_set().push(&m, &x);
+                            }
+                        });
+                    }
+                });
+            });
+            gc.clear(&m);
+            assert_eq!(gc.get_node_count(), 1 + 4 * 50);
+            assert_eq!(root.ref_set().len(), 200);
+            let sum = (0..200)
+                .map(|i| root.ref_set().get(&m, i).unwrap().value().0)
+                .sum::<usize>();
+            assert_eq!(sum, (0..4).map(|t| t * 100 * 50 + 2450).sum::<usize>());
+            let x = root.ref_set().get(&m, 0).unwrap();
+            root.ref_set().clear(&m);
+            gc.clear(&m);
+            assert_eq!(gc.get_node_count(), 2);
+            drop(x);
+            gc.clear(&m);
+            assert_eq!(gc.get_node_count(), 1);
+        });
+    }
+
+    #[test]
+    fn sync_gc_mutators() {
+        use crate::sync::{
+            scope_gc_sync, SyncGc, SyncNode, SyncStrongRef, SyncStrongVec, SyncTarget,
+        };
+
+        struct S(usize);
+
+        impl SyncTarget for S {
+            type RefObject<'gc> = (
+                SyncStrongRef<'gc, SyncNode<'gc, S>>,
+                SyncStrongVec<'gc, SyncNode<'gc, S>>,
+            );
+        }
+
+        scope_gc_sync(Default::default(), |gc: SyncGc| {
+            let mut a = gc.mutator();
+            let b = gc.mutator();
+            let x = gc.new(&a, S(0));
+            let y = gc.new(&b, S(1));
+            x.ref_set().0.set_ref(&a, &y);
+            x.ref_set().1.push(&b, &y);
+            assert!(x.ref_set().1.get(&a, 1).is_none());
+            assert!(x.ref_set().1.set(&a, 1, &x).is_none());
+            assert_eq!(x.ref_set().1.set(&a, 0, &x).unwrap().value().0, 1);
+            drop(y);
+            gc.clear(&a);
+            assert_eq!(gc.get_node_count(), 2);
+            x.ref_set().0.set(&b, None);
+            drop(b);
+            a.blocking(|| {
+                std::thread::scope(|scope| {
+                    scope.spawn(|| {
+                        let m = gc.mutator();
+                        gc.clear(&m);
+                    });
+                });
+            });
+            assert_eq!(gc.get_node_count(), 1);
+            assert!(x.ref_set().1.get(&a, 0).unwrap() == x);
+        });
+    }
+
+    #[test]
+    fn sync_gc_nested_mutator() {
+        use crate::sync::{scope_gc_sync, SyncGc};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::Duration;
+
+        scope_gc_sync(Default::default(), |gc: SyncGc| {
+            let mut m = gc.mutator();
+            let cleared = AtomicBool::new(false);
+            m.blocking(|| {
+                let inner = gc.mutator();
+                std::thread::scope(|scope| {
+                    scope.spawn(|| {
+                        let m = gc.mutator();
+                        gc.clear(&m);
+                        cleared.store(true, Ordering::SeqCst);
+                    });
+                    // `inner` 尚未到达安全点，回收必须等待
+                    std::thread::sleep(Duration::from_millis(50));
+                    assert!(!cleared.load(Ordering::SeqCst));
+                    while !cleared.load(Ordering::SeqCst) {
+                        inner.safepoint();
+                        std::thread::yield_now();
+                    }
+                });
+            });
+        });
+    }
 }
@@ -0,0 +1,61 @@
+use crate::node::NodeTrait;
+use crate::root_ref::RootRef;
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+use std::ptr::NonNull;
+
+/// 指向被管理对象内部的根引用
+///
+/// 通过 [`RootRef::map`] 创建，持有对象的根引用以保证对象存活
+///
+pub struct MappedRootRef<'gc, N: ?Sized + NodeTrait<'gc> + 'gc, U: ?Sized> {
+    owner: RootRef<'gc, N>,
+    ptr: NonNull<U>,
+}
+
+impl<'gc, N: ?Sized + NodeTrait<'gc> + 'gc, U: ?Sized> MappedRootRef<'gc, N, U> {
+    pub(crate) fn new<F: FnOnce(&N) -> &U>(owner: RootRef<'gc, N>, f: F) -> Self {
+        let ptr = NonNull::from(f(&owner));
+        Self { owner, ptr }
+    }
+
+    /// 获取所属对象的根引用
+    ///
+    #[inline(always)]
+    pub fn owner(this: &Self) -> &RootRef<'gc, N> {
+        &this.owner
+    }
+
+    /// 进一步投影到内部的值
+    ///
+    pub fn map<V: ?Sized, F: FnOnce(&U) -> &V>(this: Self, f: F) -> MappedRootRef<'gc, N, V> {
+        let ptr = NonNull::from(f(&this));
+        MappedRootRef {
+            owner: this.owner,
+            ptr,
+        }
+    }
+}
+
+impl<'gc, N: ?Sized + NodeTrait<'gc> + 'gc, U: ?Sized> Clone for MappedRootRef<'gc, N, U> {
+    fn clone(&self) -> Self {
+        Self {
+            owner: self.owner.clone(),
+            ptr: self.ptr,
+        }
+    }
+}
+
+impl<'gc, N: ?Sized + NodeTrait<'gc> + 'gc, U: ?Sized + Debug> Debug for MappedRootRef<'gc, N, U> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("MappedRootRef").field(&self.deref()).finish()
+    }
+}
+
+impl<'gc, N: ?Sized + NodeTrait<'gc> + 'gc, U: ?Sized> Deref for MappedRootRef<'gc, N, U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.as_ref() }
+    }
+}
@@ -1,10 +1,17 @@
 use crate::node::State::{Trace, Unknown};
+use crate::raw_type::RawType;
 use crate::target::{RefSet, Target};
-use std::cell::Cell;
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::any::TypeId;
+use std::cell::{Cell, RefCell};
 use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::mem::{align_of, offset_of, size_of, transmute};
 use std::ops::Deref;
+use std::ptr::{addr_of, addr_of_mut, drop_in_place, slice_from_raw_parts_mut, NonNull};
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[repr(u8)]
 pub enum State {
     /// 未知
     Unknown,
@@ -16,19 +23,50 @@ pub enum State {
     Trace,
 }
 
+/// 对象的标识
+///
+/// 在对象存活期间保持不变，并且在同一个 GC 中不会被重复使用
+///
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct NodeId(u64);
+
+impl NodeId {
+    pub(crate) fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// 获取标识的数值
+    ///
+    #[inline(always)]
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
 pub struct NodeHead {
     root: Cell<usize>,
     marker: Cell<State>,
+    id: NodeId,
 }
 
 impl NodeHead {
     pub fn new() -> Self {
+        Self::with_id(NodeId(0))
+    }
+
+    pub(crate) fn with_id(id: NodeId) -> Self {
         Self {
             root: Cell::new(0),
             marker: Cell::new(Unknown),
+            id,
         }
     }
 
+    #[inline(always)]
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
     #[inline(always)]
     pub(crate) fn set_marker(&self, state: State) {
         self.marker.set(state);
@@ -55,22 +93,26 @@ impl NodeHead {
     ) -> &'s NodeHead {
         // # 安全
         //
-        // [`NodeTrait`] 只被 [`Node`] 实现
-        // [`Node`] 以 `C` 布局排列
-        // `head` 是 [`Node`] 的第一个成员
+        // [`NodeTrait`] 只被 [`Node`] 和 [`NodePrefix`] 实现
+        // 它们都以 `C` 布局排列
+        // `head` 是它们的第一个成员
         //
         unsafe { &*(node as *const T as *const Self) }
     }
 }
 
+/// 被管理的对象
+///
+/// `value` 位于末尾，因此 `T` 可以是切片、字符串或特征对象这样的动态大小类型
+///
 #[repr(C)]
-pub struct Node<'gc, T: Target> {
+pub struct Node<'gc, T: ?Sized + Target> {
     head: NodeHead,
     pub ref_set: T::RefObject<'gc>,
     pub value: T,
 }
 
-impl<'gc, T: Target> Node<'gc, T> {
+impl<'gc, T: ?Sized + Target> Node<'gc, T> {
     #[inline(always)]
     pub fn ref_set(&self) -> &T::RefObject<'gc> {
         &self.ref_set
@@ -80,17 +122,168 @@ impl<'gc, T: Target> Node<'gc, T> {
     pub fn value(&self) -> &T {
         &self.value
     }
+}
 
-    pub(crate) unsafe fn new(value: T) -> Self {
+impl<'gc, T: Target> Node<'gc, T> {
+    pub(crate) unsafe fn new(value: T, id: NodeId) -> Self {
         Self {
-            head: NodeHead::new(),
+            head: NodeHead::with_id(id),
             ref_set: T::RefObject::build(),
             value,
         }
     }
+
+    /// 初始化除值以外的部分
+    ///
+    pub(crate) unsafe fn init_uninit(ptr: *mut Self, id: NodeId) {
+        addr_of_mut!((*ptr).head).write(NodeHead::with_id(id));
+        addr_of_mut!((*ptr).ref_set).write(T::RefObject::build());
+    }
+
+    /// 初始化值
+    ///
+    pub(crate) unsafe fn init_value(ptr: *mut Self, value: T) {
+        addr_of_mut!((*ptr).value).write(value);
+    }
+}
+
+impl<'gc, T> Node<'gc, RawType<[T]>> {
+    /// 长度为 `len` 的切片对象的内存布局
+    ///
+    fn slice_layout(len: usize) -> Layout {
+        let offset = offset_of!(Node<'gc, RawType<[T; 0]>>, value);
+        let align = align_of::<Node<'gc, RawType<[T; 0]>>>();
+        let size = size_of::<T>()
+            .checked_mul(len)
+            .and_then(|size| size.checked_add(offset))
+            .expect("capacity overflow");
+        Layout::from_size_align(size, align)
+            .expect("capacity overflow")
+            .pad_to_align()
+    }
+
+    /// 分配长度为 `len` 的切片对象，只初始化对象头，元素需要调用者通过 [`Node::slice_data`] 写入
+    ///
+    pub(crate) unsafe fn alloc_slice(len: usize, id: NodeId) -> *mut Self {
+        let layout = Self::slice_layout(len);
+        let data = alloc(layout);
+        if data.is_null() {
+            handle_alloc_error(layout);
+        }
+        let ptr = slice_from_raw_parts_mut(data.cast::<T>(), len) as *mut Self;
+        addr_of_mut!((*ptr).head).write(NodeHead::with_id(id));
+        ptr
+    }
+
+    /// 获取切片对象第一个元素的指针
+    ///
+    pub(crate) unsafe fn slice_data(ptr: *mut Self) -> *mut T {
+        addr_of_mut!((*ptr).value.0).cast::<T>()
+    }
+
+    /// 销毁由 [`Node::alloc_slice`] 分配的切片对象并释放内存
+    ///
+    pub(crate) unsafe fn drop_slice(head: NonNull<NodeHead>, len: usize) {
+        let ptr = slice_from_raw_parts_mut(head.as_ptr().cast::<T>(), len) as *mut Self;
+        drop_in_place(ptr);
+        dealloc(ptr.cast(), Self::slice_layout(len));
+    }
 }
 
-impl<'gc, T: Target> Deref for Node<'gc, T> {
+/// 正在通过 [`crate::gc::Gc::new_cyclic`] 构造的对象
+///
+/// 此时对象的值尚未初始化，只有引用集合可以使用
+///
+/// 指向对象自身的引用通过 [`CyclicNode::link`] 登记，在构造完成后才会被写入，因此构造期间任何途径都无法读取未初始化的值
+///
+pub struct CyclicNode<'n, 'gc, T: Target> {
+    _marker: PhantomData<&'n Node<'gc, T>>,
+    ptr: *const Node<'gc, T>,
+    links: RefCell<Vec<Link<'n, 'gc, T>>>,
+}
+
+/// 构造完成后执行的操作
+///
+type Link<'n, 'gc, T> = Box<dyn FnOnce(&Node<'gc, T>) + 'n>;
+
+impl<'n, 'gc, T: Target> CyclicNode<'n, 'gc, T> {
+    pub(crate) unsafe fn new(ptr: *const Node<'gc, T>) -> Self {
+        Self {
+            _marker: PhantomData,
+            ptr,
+            links: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// 获取对象的引用集合
+    ///
+    /// 返回的引用不能被登记的操作捕获，因为构造恐慌时对象会在这些操作销毁之后释放
+    ///
+    /// ```compile_fail
+    /// use scope_gc::gc::{scope_gc, Gc};
+    /// use scope_gc::node::{CyclicNode, Node};
+    /// use scope_gc::strong_ref::StrongRef;
+    /// use scope_gc::target::Target;
+    ///
+    /// struct C;
+    ///
+    /// impl Target for C {
+    ///     type RefObject<'gc> = StrongRef<'gc, Node<'gc, C>>;
+    /// }
+    ///
+    /// scope_gc(Default::default(), |gc: Gc| {
+    ///     gc.new_cyclic(|this: &CyclicNode<C>| {
+    ///         let ref_set = this.ref_set();
+    ///         this.link(move |_| drop(ref_set));
+    ///         C
+    ///     });
+    /// });
+    /// ```
+    ///
+    #[inline(always)]
+    pub fn ref_set(&self) -> &T::RefObject<'gc> {
+        unsafe { &*addr_of!((*self.ptr).ref_set) }
+    }
+
+    /// 获取指向对象的指针
+    ///
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *const Node<'gc, T> {
+        self.ptr
+    }
+
+    /// 登记一个在构造完成后执行的操作，通常用于将对象写入其他对象的引用集合
+    ///
+    /// 例如 `this.link(move |this| child.ref_set().set_ref(this))`
+    ///
+    /// 所有操作按照登记顺序执行，此时对象已经被托管并且其值已经初始化
+    ///
+    /// 如果构造过程恐慌，登记的操作不会执行
+    ///
+    pub fn link<F: FnOnce(&Node<'gc, T>) + 'n>(&self, f: F) {
+        self.links.borrow_mut().push(Box::new(f));
+    }
+
+    /// 获取对象的前缀视图，构造期间作为根参与追踪
+    ///
+    pub(crate) fn as_dyn_node(&self) -> &'n dyn NodeTrait<'gc> {
+        // # 安全
+        //
+        // 前缀视图不包含尚未初始化的值
+        //
+        unsafe { &*(self.ptr as *const NodePrefix<'gc, T>) }
+    }
+
+    /// 执行全部登记的操作
+    ///
+    pub(crate) fn finish(self, node: &Node<'gc, T>) {
+        for f in self.links.into_inner() {
+            f(node);
+        }
+    }
+}
+
+impl<'gc, T: ?Sized + Target> Deref for Node<'gc, T> {
     type Target = T;
 
     #[inline(always)]
@@ -99,7 +292,33 @@ impl<'gc, T: Target> Deref for Node<'gc, T> {
     }
 }
 
-/// 此特征唯一由 [`Node`] 实现
+/// 获取忽略生命周期参数的类型标识
+///
+pub(crate) fn type_id_of<T: ?Sized>() -> TypeId {
+    trait NonStaticAny {
+        fn get_type_id(&self) -> TypeId
+        where
+            Self: 'static;
+    }
+
+    impl<T: ?Sized> NonStaticAny for PhantomData<T> {
+        fn get_type_id(&self) -> TypeId
+        where
+            Self: 'static,
+        {
+            TypeId::of::<T>()
+        }
+    }
+
+    let phantom = PhantomData::<T>;
+    // # 安全
+    //
+    // `get_type_id` 只读取类型标识，不会访问任何具有生命周期的数据
+    //
+    unsafe { transmute::<&dyn NonStaticAny, &(dyn NonStaticAny + 'static)>(&phantom).get_type_id() }
+}
+
+/// 此特征唯一由 [`Node`] 及其前缀视图实现
 ///
 /// # 安全
 ///
@@ -112,25 +331,34 @@ pub unsafe trait NodeTrait<'gc>: Debug {
 
     fn root(&self) -> usize;
 
+    /// 对象的标识
+    ///
+    fn id(&self) -> NodeId;
+
+    /// 被管理值的类型标识，忽略所有生命周期参数
+    ///
+    fn value_type_id(&self) -> TypeId;
+
     unsafe fn mark_and_collect(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>);
 
     unsafe fn pre_drop(&self);
 }
 
-impl<'gc, T: Target> Debug for Node<'gc, T> {
+impl<'gc, T: ?Sized + Target> Debug for Node<'gc, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut s = f.debug_struct("Node");
         s.field("#", &(self as *const _));
+        s.field("id", &self.head.id.0);
         s.field("root", &self.head.root.get());
         s.field("ref_set", &self.ref_set);
         s.finish()
     }
 }
 
-unsafe impl<'gc, T: Target> NodeTrait<'gc> for Node<'gc, T> {
+unsafe impl<'gc, T: ?Sized + Target> NodeTrait<'gc> for Node<'gc, T> {
     #[inline(always)]
     fn as_dyn_node(&self) -> &dyn NodeTrait<'gc> {
-        self
+        NodePrefix::from_node(self)
     }
 
     #[inline(always)]
@@ -143,6 +371,16 @@ unsafe impl<'gc, T: Target> NodeTrait<'gc> for Node<'gc, T> {
         self.head.root.get()
     }
 
+    #[inline(always)]
+    fn id(&self) -> NodeId {
+        self.head.id
+    }
+
+    #[inline(always)]
+    fn value_type_id(&self) -> TypeId {
+        type_id_of::<T>()
+    }
+
     /// 收集此对象的引用情况
     ///
     /// # 安全
@@ -173,3 +411,81 @@ unsafe impl<'gc, T: Target> NodeTrait<'gc> for Node<'gc, T> {
         self.value.pre_drop(&self.ref_set);
     }
 }
+
+/// 对象去除值之后的前缀部分
+///
+/// 动态大小的对象无法直接转换为 `dyn NodeTrait`，追踪时使用这个固定大小的视图代替
+///
+#[repr(C)]
+pub(crate) struct NodePrefix<'gc, T: ?Sized + Target> {
+    head: NodeHead,
+    ref_set: T::RefObject<'gc>,
+    _marker: PhantomData<*const T>,
+}
+
+impl<'gc, T: ?Sized + Target> NodePrefix<'gc, T> {
+    #[inline(always)]
+    fn from_node<'n>(node: &'n Node<'gc, T>) -> &'n Self {
+        // # 安全
+        //
+        // 两者都以 `C` 布局排列，且 `value` 之前的成员完全相同
+        //
+        unsafe { &*(node as *const Node<'gc, T> as *const Self) }
+    }
+}
+
+impl<'gc, T: ?Sized + Target> Debug for NodePrefix<'gc, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_struct("Node");
+        s.field("#", &(self as *const _));
+        s.field("id", &self.head.id.0);
+        s.field("root", &self.head.root.get());
+        s.field("ref_set", &self.ref_set);
+        s.finish()
+    }
+}
+
+unsafe impl<'gc, T: ?Sized + Target> NodeTrait<'gc> for NodePrefix<'gc, T> {
+    #[inline(always)]
+    fn as_dyn_node(&self) -> &dyn NodeTrait<'gc> {
+        self
+    }
+
+    #[inline(always)]
+    fn head(&self) -> &NodeHead {
+        &self.head
+    }
+
+    #[inline(always)]
+    fn root(&self) -> usize {
+        self.head.root.get()
+    }
+
+    #[inline(always)]
+    fn id(&self) -> NodeId {
+        self.head.id
+    }
+
+    #[inline(always)]
+    fn value_type_id(&self) -> TypeId {
+        type_id_of::<T>()
+    }
+
+    #[inline(always)]
+    unsafe fn mark_and_collect(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+        match self.head.get_marker() {
+            Trace => {
+                self.head.marker.set(State::Strong);
+                self.ref_set.collect(stack);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    /// 前缀视图无法访问值，预析构总是由完整的对象执行
+    ///
+    #[inline(always)]
+    unsafe fn pre_drop(&self) {}
+}
@@ -0,0 +1,103 @@
+use crate::gc::NoGcGuard;
+use crate::gc_ref::GcRef;
+use crate::node::State::{Trace, Unknown};
+use crate::node::{NodeHead, NodeTrait};
+use crate::root_ref::RootRef;
+use crate::target::RefSet;
+use std::cell::OnceCell;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// 只能写入一次的强引用位
+///
+/// 适用于托管后立即设置且之后不再改变的引用
+///
+pub struct OnceRef<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> {
+    _marker: PhantomData<*mut &'gc ()>,
+    cell: OnceCell<NonNull<T>>,
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> OnceRef<'gc, T> {
+    /// 写入引用，如果已经写入过则将传入的引用通过 [`Err`] 返回
+    ///
+    #[inline(always)]
+    pub fn init<'r>(&self, r: &'r T) -> Result<(), &'r T> {
+        self.cell.set(NonNull::from(r)).map_err(|_| r)
+    }
+
+    /// 获取引用
+    ///
+    /// # 恐慌
+    ///
+    /// 如果尚未写入则恐慌
+    ///
+    #[inline(always)]
+    pub fn get(&self) -> RootRef<'gc, T> {
+        match self.try_get() {
+            Some(r) => r,
+            None => panic!("OnceRef is not initialized"),
+        }
+    }
+
+    /// 获取引用，如果尚未写入则返回 [`None`]
+    ///
+    #[inline(always)]
+    pub fn try_get(&self) -> Option<RootRef<'gc, T>> {
+        self.cell.get().map(|r| unsafe { RootRef::new(r.as_ref()) })
+    }
+
+    /// 在禁止回收期间获取不增加根引用计数的引用
+    ///
+    /// # 恐慌
+    ///
+    /// 如果尚未写入则恐慌
+    ///
+    #[inline(always)]
+    pub fn get_ref<'r>(&self, _guard: &'r NoGcGuard<'_, 'gc, '_>) -> GcRef<'r, T> {
+        match self.cell.get() {
+            Some(r) => GcRef::new(unsafe { r.as_ref() }),
+            None => panic!("OnceRef is not initialized"),
+        }
+    }
+
+    /// 是否已经写入
+    ///
+    #[inline(always)]
+    pub fn is_init(&self) -> bool {
+        self.cell.get().is_some()
+    }
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> Debug for OnceRef<'gc, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_tuple("OnceRef");
+        if let Some(r) = self.cell.get() {
+            s.field(r);
+        } else {
+            s.field(&None::<()>);
+        }
+        s.finish()
+    }
+}
+
+unsafe impl<'gc, T: ?Sized + NodeTrait<'gc>> RefSet<'gc> for OnceRef<'gc, T> {
+    #[inline(always)]
+    unsafe fn build() -> Self {
+        Self {
+            _marker: PhantomData,
+            cell: OnceCell::new(),
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn collect(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+        if let Some(r) = self.cell.get() {
+            let r = r.as_ref();
+            if NodeHead::from_node_trait(r).get_marker() == Unknown {
+                NodeHead::from_node_trait(r).set_marker(Trace);
+                stack.push(r.as_dyn_node());
+            }
+        }
+    }
+}
@@ -1,6 +1,9 @@
-use crate::node::{NodeHead, NodeTrait};
+use crate::mapped_root_ref::MappedRootRef;
+use crate::node::{type_id_of, Node, NodeHead, NodeTrait};
+use crate::target::Target;
 use std::fmt::{Debug, Formatter};
 use std::marker::PhantomData;
+use std::mem::forget;
 use std::ops::Deref;
 use std::ptr::NonNull;
 
@@ -13,39 +16,167 @@ pub struct RootRef<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> {
     ptr: NonNull<T>,
 }
 
-impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> RootRef<'gc, T> {
-    pub fn new(r: &T) -> Self {
-        NodeHead::from_node_trait(r).inc_root();
-        Self {
-            _marker: PhantomData,
-            ptr: NonNull::from(r),
+/// 生成根引用的公共实现，由 [`RootRef`] 和 [`crate::sync::SyncRootRef`] 共用
+///
+/// 类型需要包含 `_marker` 和 `ptr` 两个字段，`$head` 需要提供 `from_node_trait`、`inc_root` 和 `dec_root`
+///
+macro_rules! impl_root_ref {
+    ($name:ident, $node:ident, $head:ident) => {
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> $name<'gc, T> {
+            pub fn new(r: &T) -> Self {
+                $head::from_node_trait(r).inc_root();
+                Self {
+                    _marker: PhantomData,
+                    ptr: NonNull::from(r),
+                }
+            }
+
+            /// 将根引用转换为另一种类型的根引用，通常用于在稳定版中进行非固定大小转换
+            ///
+            /// 例如 `let y: RootRef<dyn NodeA> = x.coerce(|n| n as _);`
+            ///
+            /// 如果 `f` 返回的仍是同一个对象，根引用计数会被直接转移
+            ///
+            pub fn coerce<U: ?Sized + $node<'gc> + 'gc, F: FnOnce(&T) -> &U>(
+                self,
+                f: F,
+            ) -> $name<'gc, U> {
+                let ptr = NonNull::from(f(&self));
+                if ptr.as_ptr() as *const () == self.ptr.as_ptr() as *const () {
+                    std::mem::forget(self);
+                    $name {
+                        _marker: PhantomData,
+                        ptr,
+                    }
+                } else {
+                    $name::new(unsafe { ptr.as_ref() })
+                }
+            }
+
+            /// 判断两个根引用是否指向同一个对象
+            ///
+            #[inline(always)]
+            pub fn ptr_eq<U: ?Sized + $node<'gc> + 'gc>(
+                this: &Self,
+                other: &$name<'gc, U>,
+            ) -> bool {
+                this.ptr.as_ptr() as *const () == other.ptr.as_ptr() as *const ()
+            }
         }
-    }
+
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> Drop for $name<'gc, T> {
+            fn drop(&mut self) {
+                $head::from_node_trait(unsafe { self.ptr.as_ref() }).dec_root();
+            }
+        }
+
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> Clone for $name<'gc, T> {
+            fn clone(&self) -> Self {
+                Self::new(self)
+            }
+        }
+
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> Debug for $name<'gc, T> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                f.debug_tuple(stringify!($name))
+                    .field(&self.deref())
+                    .finish()
+            }
+        }
+
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> PartialEq for $name<'gc, T> {
+            fn eq(&self, other: &Self) -> bool {
+                Self::ptr_eq(self, other)
+            }
+        }
+
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> Eq for $name<'gc, T> {}
+
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> PartialOrd for $name<'gc, T> {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        /// 按照对象的 [`crate::node::NodeId`] 排序
+        ///
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> Ord for $name<'gc, T> {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.id().cmp(&other.id())
+            }
+        }
+
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> std::hash::Hash for $name<'gc, T> {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                (self.ptr.as_ptr() as *const ()).hash(state);
+            }
+        }
+
+        impl<'gc, T: ?Sized + $node<'gc> + 'gc> Deref for $name<'gc, T> {
+            type Target = T;
+
+            fn deref(&self) -> &Self::Target {
+                unsafe { self.ptr.as_ref() }
+            }
+        }
+    };
 }
 
-impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> Drop for RootRef<'gc, T> {
-    fn drop(&mut self) {
-        NodeHead::from_node_trait(self.deref().deref()).dec_root();
+pub(crate) use impl_root_ref;
+
+impl_root_ref!(RootRef, NodeTrait, NodeHead);
+
+impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> RootRef<'gc, T> {
+    /// 投影到被管理对象内部的值，同时保持对象存活
+    ///
+    /// 类似于 [`std::cell::Ref::map`]
+    ///
+    pub fn map<U: ?Sized, F: FnOnce(&T) -> &U>(this: Self, f: F) -> MappedRootRef<'gc, T, U> {
+        MappedRootRef::new(this, f)
     }
-}
 
-impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> Clone for RootRef<'gc, T> {
-    fn clone(&self) -> Self {
-        Self::new(self)
+    /// 判断引用的对象所管理的值是否为 `U`，忽略生命周期参数
+    ///
+    #[inline(always)]
+    pub fn is<U: Target>(&self) -> bool {
+        self.value_type_id() == type_id_of::<U>()
     }
-}
 
-impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> Debug for RootRef<'gc, T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("RootRef").field(&self.deref()).finish()
+    /// 尝试转换为具体类型的根引用，失败时原样返回
+    ///
+    /// # 安全
+    ///
+    /// 类型标识忽略生命周期参数，调用者必须保证 `U` 的生命周期参数与实际值一致
+    ///
+    pub unsafe fn downcast<U: Target>(self) -> Result<RootRef<'gc, Node<'gc, U>>, Self> {
+        if self.is::<U>() {
+            Ok(self.downcast_unchecked())
+        } else {
+            Err(self)
+        }
     }
-}
 
-impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> Deref for RootRef<'gc, T> {
-    type Target = T;
+    /// 尝试获取具体类型的引用
+    ///
+    /// # 安全
+    ///
+    /// 参见 [`RootRef::downcast`]
+    ///
+    pub unsafe fn downcast_ref<U: Target>(&self) -> Option<&Node<'gc, U>> {
+        if self.is::<U>() {
+            Some(self.ptr.cast().as_ref())
+        } else {
+            None
+        }
+    }
 
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.ptr.as_ref() }
+    unsafe fn downcast_unchecked<U: Target>(self) -> RootRef<'gc, Node<'gc, U>> {
+        let ptr = self.ptr.cast();
+        forget(self);
+        RootRef {
+            _marker: PhantomData,
+            ptr,
+        }
     }
 }
 
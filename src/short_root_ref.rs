@@ -0,0 +1,50 @@
+use crate::node::{NodeHead, NodeTrait};
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr::NonNull;
+
+/// 指向存活时间短于 `'gc` 的对象的外部根引用
+///
+/// 与 [`crate::root_ref::RootRef`] 相同，但不要求 `T: 'gc`，参见 [`crate::gc::Gc::new_short_lived`]
+///
+pub struct ShortRootRef<'gc, T: ?Sized + NodeTrait<'gc>> {
+    _marker: PhantomData<*mut &'gc ()>,
+    ptr: NonNull<T>,
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> ShortRootRef<'gc, T> {
+    pub(crate) fn new(r: &T) -> Self {
+        NodeHead::from_node_trait(r).inc_root();
+        Self {
+            _marker: PhantomData,
+            ptr: NonNull::from(r),
+        }
+    }
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> Drop for ShortRootRef<'gc, T> {
+    fn drop(&mut self) {
+        NodeHead::from_node_trait(self.deref().deref()).dec_root();
+    }
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> Clone for ShortRootRef<'gc, T> {
+    fn clone(&self) -> Self {
+        Self::new(self)
+    }
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> Debug for ShortRootRef<'gc, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ShortRootRef").field(&self.deref()).finish()
+    }
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> Deref for ShortRootRef<'gc, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.as_ref() }
+    }
+}
@@ -1,9 +1,13 @@
+use crate::gc::NoGcGuard;
+use crate::gc_ref::GcRef;
 use crate::node::State::{Trace, Unknown};
-use crate::node::{NodeHead, NodeTrait};
+use crate::node::{NodeHead, NodeId, NodeTrait};
 use crate::root_ref::RootRef;
 use crate::target::RefSet;
 use std::cell::Cell;
+use std::cmp::Ordering;
 use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 use std::ptr::NonNull;
 
@@ -33,6 +37,76 @@ impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> StrongRef<'gc, T> {
     pub fn set_none(&self) {
         self.cell.set(None);
     }
+
+    /// 取出引用，并将其设为 [`None`]
+    ///
+    #[inline(always)]
+    pub fn take(&self) -> Option<RootRef<'gc, T>> {
+        self.cell
+            .take()
+            .map(|r| unsafe { RootRef::new(r.as_ref()) })
+    }
+
+    /// 设置新的引用，并返回旧的引用
+    ///
+    #[inline(always)]
+    pub fn replace(&self, r: &T) -> Option<RootRef<'gc, T>> {
+        self.cell
+            .replace(Some(NonNull::from(r)))
+            .map(|r| unsafe { RootRef::new(r.as_ref()) })
+    }
+
+    /// 交换两个引用位的内容
+    ///
+    #[inline(always)]
+    pub fn swap(&self, other: &Self) {
+        self.cell.swap(&other.cell);
+    }
+
+    /// 是否没有引用任何对象
+    ///
+    #[inline(always)]
+    pub fn is_none(&self) -> bool {
+        self.cell.get().is_none()
+    }
+
+    /// 是否引用了某个对象
+    ///
+    #[inline(always)]
+    pub fn is_some(&self) -> bool {
+        self.cell.get().is_some()
+    }
+
+    /// 判断是否引用了 `r` 所指向的对象
+    ///
+    #[inline(always)]
+    pub fn ptr_eq(&self, r: &T) -> bool {
+        match self.cell.get() {
+            Some(p) => p.as_ptr() as *const () == r as *const T as *const (),
+            None => false,
+        }
+    }
+
+    /// 获取引用对象的标识
+    ///
+    #[inline(always)]
+    pub fn id(&self) -> Option<NodeId> {
+        self.cell.get().map(|r| unsafe { r.as_ref() }.id())
+    }
+
+    /// 在禁止回收期间获取不增加根引用计数的引用
+    ///
+    #[inline(always)]
+    pub fn get_ref<'r>(&self, _guard: &'r NoGcGuard<'_, 'gc, '_>) -> Option<GcRef<'r, T>> {
+        self.cell.get().map(|r| GcRef::new(unsafe { r.as_ref() }))
+    }
+
+    /// 在禁止回收期间不增加根引用计数地访问引用的对象
+    ///
+    #[inline(always)]
+    pub fn with<R, F: FnOnce(Option<&T>) -> R>(&self, _guard: &NoGcGuard<'_, 'gc, '_>, f: F) -> R {
+        f(self.cell.get().map(|r| unsafe { r.as_ref() }))
+    }
 }
 
 impl<'gc, T: ?Sized + NodeTrait<'gc>> Debug for StrongRef<'gc, T> {
@@ -47,6 +121,37 @@ impl<'gc, T: ?Sized + NodeTrait<'gc>> Debug for StrongRef<'gc, T> {
     }
 }
 
+/// 按照引用的对象判断是否相等，两个 [`None`] 相等
+///
+impl<'gc, T: ?Sized + NodeTrait<'gc>> PartialEq for StrongRef<'gc, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cell.get().map(|r| r.as_ptr() as *const ())
+            == other.cell.get().map(|r| r.as_ptr() as *const ())
+    }
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> Eq for StrongRef<'gc, T> {}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> PartialOrd for StrongRef<'gc, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 按照对象的 [`NodeId`] 排序，[`None`] 排在最前
+///
+impl<'gc, T: ?Sized + NodeTrait<'gc>> Ord for StrongRef<'gc, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id().cmp(&other.id())
+    }
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> Hash for StrongRef<'gc, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.cell.get().map(|r| r.as_ptr() as *const ()).hash(state);
+    }
+}
+
 unsafe impl<'gc, T: ?Sized + NodeTrait<'gc>> RefSet<'gc> for StrongRef<'gc, T> {
     #[inline(always)]
     unsafe fn build() -> Self {
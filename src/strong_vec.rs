@@ -1,3 +1,5 @@
+use crate::gc::NoGcGuard;
+use crate::gc_ref::GcRef;
 use crate::node::State::{Trace, Unknown};
 use crate::node::{NodeHead, NodeTrait};
 use crate::root_ref::RootRef;
@@ -25,6 +27,24 @@ impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> StrongVec<'gc, T> {
             .ok_or(())
     }
 
+    /// 在禁止回收期间获取不增加根引用计数的引用
+    ///
+    /// 与 [`StrongVec::get`] 一致，越界时返回 `Err(())`
+    ///
+    #[allow(clippy::result_unit_err)]
+    #[inline(always)]
+    pub fn get_ref<'r>(
+        &self,
+        index: usize,
+        _guard: &'r NoGcGuard<'_, 'gc, '_>,
+    ) -> Result<GcRef<'r, T>, ()> {
+        self.vec
+            .borrow()
+            .get(index)
+            .map(|i| GcRef::new(unsafe { i.get().as_ref() }))
+            .ok_or(())
+    }
+
     #[inline(always)]
     pub fn set(&self, index: usize, r: &T) -> Result<(), ()> {
         self.vec
@@ -0,0 +1,971 @@
+//! 线程安全的 GC
+//!
+//! 结构与单线程版本一致，但对象头使用原子计数，引用位使用锁保护
+//!
+//! 每个访问引用或托管对象的线程都需要持有一个 [`Mutator`]，回收时会等待其他 [`Mutator`] 到达安全点
+//!
+//! 启用 [`SyncConfig::concurrent_mark`] 后，标记在辅助线程上进行，期间 [`Mutator`] 可以继续运行
+//! 覆盖或移除引用时会记录旧值（起始快照写屏障），新分配的对象直接视为存活，最后短暂暂停以完成标记并清扫
+//!
+
+use crate::gc::Config;
+use crate::node::State::{Strong, Trace, Unknown};
+use crate::node::{type_id_of, NodeId, State};
+use crate::raw_type::RawType;
+use crate::root_ref::impl_root_ref;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::mem::{replace, swap, take, transmute};
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{current, ThreadId};
+
+/// 获取锁，忽略中毒
+///
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// 可以被线程安全的 GC 管理的数据部分，参见 [`crate::target::Target`]
+pub trait SyncTarget: Send + Sync {
+    /// 对应的 GC 引用部分
+    type RefObject<'gc>: SyncRefSet<'gc>;
+
+    /// 预析构函数，参见 [`crate::target::Target::pre_drop`]
+    ///
+    /// # 安全
+    ///
+    /// 用户不得调用
+    ///
+    #[inline(always)]
+    unsafe fn pre_drop<'gc>(&self, _ref_set: &Self::RefObject<'gc>) {}
+}
+
+impl<T: ?Sized + Send + Sync> SyncTarget for RawType<T> {
+    type RefObject<'gc> = ();
+}
+
+/// 线程安全的引用部分，参见 [`crate::target::RefSet`]
+///
+/// # 安全
+///
+/// 参见 [`crate::target::RefSet`]
+///
+pub unsafe trait SyncRefSet<'gc>: Debug + Send + Sync {
+    /// 构造一个此类型的值
+    ///
+    /// # 安全
+    ///
+    /// 用户不得调用
+    ///
+    unsafe fn build() -> Self;
+
+    /// 追踪内部引用
+    ///
+    /// # 安全
+    ///
+    /// 实现必须不重不漏的遍历内部引用情况
+    ///
+    /// 用户不得调用
+    ///
+    unsafe fn collect(&self, stack: &mut Vec<&dyn SyncNodeTrait<'gc>>);
+}
+
+unsafe impl<'gc> SyncRefSet<'gc> for () {
+    #[inline(always)]
+    unsafe fn build() -> Self {}
+
+    #[inline(always)]
+    unsafe fn collect(&self, _stack: &mut Vec<&dyn SyncNodeTrait<'gc>>) {}
+}
+
+unsafe impl<'gc, T: SyncRefSet<'gc>, const N: usize> SyncRefSet<'gc> for [T; N] {
+    #[inline(always)]
+    unsafe fn build() -> Self {
+        std::array::from_fn(|_| T::build())
+    }
+
+    #[inline(always)]
+    unsafe fn collect(&self, stack: &mut Vec<&dyn SyncNodeTrait<'gc>>) {
+        for i in self {
+            i.collect(stack);
+        }
+    }
+}
+
+macro_rules! impl_sync_ref_set_for_tuple {
+    ($($T:ident:$i:tt),*) => {
+        unsafe impl<'gc, $($T: SyncRefSet<'gc>),*> SyncRefSet<'gc> for ($($T,)*) {
+            #[inline(always)]
+            unsafe fn build() -> Self {
+                ($($T::build(),)*)
+            }
+
+            #[inline(always)]
+            unsafe fn collect(&self, stack: &mut Vec<&dyn SyncNodeTrait<'gc>>) {
+                $(self.$i.collect(stack);)*
+            }
+        }
+    };
+}
+
+impl_sync_ref_set_for_tuple!(A:0);
+impl_sync_ref_set_for_tuple!(A:0, B:1);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2, D:3);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2, D:3, E:4);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2, D:3, E:4, F:5);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2, D:3, E:4, F:5, G:6);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10);
+impl_sync_ref_set_for_tuple!(A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11);
+
+pub struct SyncNodeHead {
+    root: AtomicUsize,
+    marker: AtomicU8,
+    id: NodeId,
+}
+
+impl SyncNodeHead {
+    fn with_id(id: NodeId) -> Self {
+        Self {
+            root: AtomicUsize::new(0),
+            marker: AtomicU8::new(Unknown as u8),
+            id,
+        }
+    }
+
+    #[inline(always)]
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    #[inline(always)]
+    fn get_marker(&self) -> State {
+        match self.marker.load(Ordering::Relaxed) {
+            x if x == Unknown as u8 => Unknown,
+            x if x == Strong as u8 => Strong,
+            _ => Trace,
+        }
+    }
+
+    #[inline(always)]
+    fn set_marker(&self, state: State) {
+        self.marker.store(state as u8, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    fn inc_root(&self) {
+        self.root.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    fn dec_root(&self) {
+        self.root.fetch_sub(1, Ordering::Release);
+    }
+
+    #[inline(always)]
+    fn from_node_trait<'s, 'gc, T: ?Sized + SyncNodeTrait<'gc> + 's>(node: &'s T) -> &'s Self {
+        // # 安全
+        //
+        // [`SyncNodeTrait`] 只被 [`SyncNode`] 实现
+        // [`SyncNode`] 以 `C` 布局排列
+        // `head` 是 [`SyncNode`] 的第一个成员
+        //
+        unsafe { &*(node as *const T as *const Self) }
+    }
+}
+
+/// 被线程安全的 GC 管理的对象
+///
+#[repr(C)]
+pub struct SyncNode<'gc, T: SyncTarget> {
+    head: SyncNodeHead,
+    pub ref_set: T::RefObject<'gc>,
+    pub value: T,
+}
+
+impl<'gc, T: SyncTarget> SyncNode<'gc, T> {
+    #[inline(always)]
+    pub fn ref_set(&self) -> &T::RefObject<'gc> {
+        &self.ref_set
+    }
+
+    #[inline(always)]
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    unsafe fn new(value: T, id: NodeId) -> Self {
+        Self {
+            head: SyncNodeHead::with_id(id),
+            ref_set: T::RefObject::build(),
+            value,
+        }
+    }
+}
+
+impl<'gc, T: SyncTarget> Deref for SyncNode<'gc, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<'gc, T: SyncTarget> Debug for SyncNode<'gc, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_struct("SyncNode");
+        s.field("#", &(self as *const _));
+        s.field("id", &self.head.id.get());
+        s.field("root", &self.head.root.load(Ordering::Relaxed));
+        s.field("ref_set", &self.ref_set);
+        s.finish()
+    }
+}
+
+/// 此特征唯一由 [`SyncNode`] 实现，参见 [`crate::node::NodeTrait`]
+///
+/// # 安全
+///
+/// 用户实现它总是不安全的
+///
+pub unsafe trait SyncNodeTrait<'gc>: Debug + Send + Sync {
+    fn as_dyn_node(&self) -> &dyn SyncNodeTrait<'gc>;
+
+    fn head(&self) -> &SyncNodeHead;
+
+    fn root(&self) -> usize;
+
+    /// 对象的标识
+    ///
+    fn id(&self) -> NodeId;
+
+    /// 被管理值的类型标识，忽略所有生命周期参数
+    ///
+    fn value_type_id(&self) -> TypeId;
+
+    unsafe fn mark_and_collect(&self, stack: &mut Vec<&dyn SyncNodeTrait<'gc>>);
+
+    unsafe fn pre_drop(&self);
+}
+
+unsafe impl<'gc, T: SyncTarget> SyncNodeTrait<'gc> for SyncNode<'gc, T> {
+    #[inline(always)]
+    fn as_dyn_node(&self) -> &dyn SyncNodeTrait<'gc> {
+        self
+    }
+
+    #[inline(always)]
+    fn head(&self) -> &SyncNodeHead {
+        &self.head
+    }
+
+    #[inline(always)]
+    fn root(&self) -> usize {
+        self.head.root.load(Ordering::Acquire)
+    }
+
+    #[inline(always)]
+    fn id(&self) -> NodeId {
+        self.head.id
+    }
+
+    #[inline(always)]
+    fn value_type_id(&self) -> TypeId {
+        type_id_of::<T>()
+    }
+
+    #[inline(always)]
+    unsafe fn mark_and_collect(&self, stack: &mut Vec<&dyn SyncNodeTrait<'gc>>) {
+        match self.head.get_marker() {
+            Trace => {
+                self.head.set_marker(Strong);
+                self.ref_set.collect(stack);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn pre_drop(&self) {
+        self.value.pre_drop(&self.ref_set);
+    }
+}
+
+/// 将引用的对象标记为待追踪
+///
+#[inline(always)]
+fn trace<'a, 'gc, T: ?Sized + SyncNodeTrait<'gc>>(
+    r: &'a T,
+    stack: &mut Vec<&'a dyn SyncNodeTrait<'gc>>,
+) {
+    if SyncNodeHead::from_node_trait(r).get_marker() == Unknown {
+        SyncNodeHead::from_node_trait(r).set_marker(Trace);
+        stack.push(r.as_dyn_node());
+    }
+}
+
+/// 线程安全的外部根引用，参见 [`crate::root_ref::RootRef`]
+///
+/// 可以在任意线程中克隆和释放，不需要 [`Mutator`]
+///
+pub struct SyncRootRef<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> {
+    _marker: PhantomData<fn(&'gc ()) -> &'gc ()>,
+    ptr: NonNull<T>,
+}
+
+unsafe impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> Send for SyncRootRef<'gc, T> {}
+
+unsafe impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> Sync for SyncRootRef<'gc, T> {}
+
+impl_root_ref!(SyncRootRef, SyncNodeTrait, SyncNodeHead);
+
+/// 为引用位中的指针创建根引用
+///
+/// # 安全
+///
+/// `r` 必须指向仍被管理的对象
+///
+#[inline(always)]
+unsafe fn root<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc>(r: NonNull<T>) -> SyncRootRef<'gc, T> {
+    SyncRootRef::new(r.as_ref())
+}
+
+/// 线程安全的可以为 [`None`] 的强引用位，参见 [`crate::strong_ref::StrongRef`]
+///
+/// 读写需要 [`Mutator`]
+///
+pub struct SyncStrongRef<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> {
+    _marker: PhantomData<fn(&'gc ()) -> &'gc ()>,
+    cell: Mutex<Option<NonNull<T>>>,
+}
+
+unsafe impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> Send for SyncStrongRef<'gc, T> {}
+
+unsafe impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> Sync for SyncStrongRef<'gc, T> {}
+
+impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> SyncStrongRef<'gc, T> {
+    #[inline(always)]
+    pub fn get(&self, _mutator: &Mutator<'gc, '_>) -> Option<SyncRootRef<'gc, T>> {
+        lock(&self.cell).map(|r| unsafe { root(r) })
+    }
+
+    #[inline(always)]
+    pub fn set(&self, mutator: &Mutator<'gc, '_>, r: Option<&T>) {
+        let old = replace(&mut *lock(&self.cell), r.map(NonNull::from));
+        mutator.barrier(old);
+    }
+
+    #[inline(always)]
+    pub fn set_ref(&self, mutator: &Mutator<'gc, '_>, r: &T) {
+        self.set(mutator, Some(r));
+    }
+
+    /// 取出引用，并将其设为 [`None`]
+    ///
+    #[inline(always)]
+    pub fn take(&self, mutator: &Mutator<'gc, '_>) -> Option<SyncRootRef<'gc, T>> {
+        let old = lock(&self.cell).take();
+        mutator.barrier(old);
+        old.map(|r| unsafe { root(r) })
+    }
+
+    #[inline(always)]
+    pub fn is_some(&self) -> bool {
+        lock(&self.cell).is_some()
+    }
+}
+
+impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> Debug for SyncStrongRef<'gc, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SyncStrongRef")
+            .field(&*lock(&self.cell))
+            .finish()
+    }
+}
+
+unsafe impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> SyncRefSet<'gc> for SyncStrongRef<'gc, T> {
+    #[inline(always)]
+    unsafe fn build() -> Self {
+        Self {
+            _marker: PhantomData,
+            cell: Mutex::new(None),
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn collect(&self, stack: &mut Vec<&dyn SyncNodeTrait<'gc>>) {
+        if let Some(r) = *lock(&self.cell) {
+            trace(r.as_ref(), stack);
+        }
+    }
+}
+
+/// 线程安全的变长强引用数组，参见 [`crate::strong_vec::StrongVec`]
+///
+/// 读写需要 [`Mutator`]
+///
+pub struct SyncStrongVec<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> {
+    _marker: PhantomData<fn(&'gc ()) -> &'gc ()>,
+    vec: Mutex<Vec<NonNull<T>>>,
+}
+
+unsafe impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> Send for SyncStrongVec<'gc, T> {}
+
+unsafe impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> Sync for SyncStrongVec<'gc, T> {}
+
+impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> SyncStrongVec<'gc, T> {
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        lock(&self.vec).len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        lock(&self.vec).is_empty()
+    }
+
+    #[inline(always)]
+    pub fn push(&self, _mutator: &Mutator<'gc, '_>, r: &T) {
+        lock(&self.vec).push(NonNull::from(r));
+    }
+
+    #[inline(always)]
+    pub fn pop(&self, mutator: &Mutator<'gc, '_>) -> Option<SyncRootRef<'gc, T>> {
+        let old = lock(&self.vec).pop();
+        mutator.barrier(old);
+        old.map(|r| unsafe { root(r) })
+    }
+
+    /// 获取第 `index` 个引用，越界时返回 [`None`]
+    ///
+    #[inline(always)]
+    pub fn get(&self, _mutator: &Mutator<'gc, '_>, index: usize) -> Option<SyncRootRef<'gc, T>> {
+        lock(&self.vec).get(index).map(|&r| unsafe { root(r) })
+    }
+
+    /// 设置第 `index` 个引用，并返回旧的引用，越界时返回 [`None`]
+    ///
+    #[inline(always)]
+    pub fn set(
+        &self,
+        mutator: &Mutator<'gc, '_>,
+        index: usize,
+        r: &T,
+    ) -> Option<SyncRootRef<'gc, T>> {
+        let old = replace(lock(&self.vec).get_mut(index)?, NonNull::from(r));
+        mutator.barrier(Some(old));
+        Some(unsafe { root(old) })
+    }
+
+    #[inline(always)]
+    pub fn clear(&self, mutator: &Mutator<'gc, '_>) {
+        let old = take(&mut *lock(&self.vec));
+        for r in old {
+            mutator.barrier(Some(r));
+        }
+    }
+}
+
+impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> Debug for SyncStrongVec<'gc, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SyncStrongVec")
+            .field(&*lock(&self.vec))
+            .finish()
+    }
+}
+
+unsafe impl<'gc, T: ?Sized + SyncNodeTrait<'gc> + 'gc> SyncRefSet<'gc> for SyncStrongVec<'gc, T> {
+    #[inline(always)]
+    unsafe fn build() -> Self {
+        Self {
+            _marker: PhantomData,
+            vec: Mutex::new(Vec::new()),
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn collect(&self, stack: &mut Vec<&dyn SyncNodeTrait<'gc>>) {
+        for &r in lock(&self.vec).iter() {
+            trace(r.as_ref(), stack);
+        }
+    }
+}
+
+/// 线程安全的 GC 的配置项
+///
+/// 除了结构体字面量，也可以通过 [`SyncConfig::default`] 和 `with_*` 方法构造
+///
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SyncConfig {
+    /// 与单线程版本共用的配置项，其中 [`Config::ambient`] 不适用于线程安全的 GC
+    pub gc: Config,
+
+    /// 是否在辅助线程上并发标记
+    pub concurrent_mark: bool,
+}
+
+impl SyncConfig {
+    /// 设置 [`SyncConfig::gc`]
+    ///
+    pub fn with_gc(self, gc: Config) -> Self {
+        Self { gc, ..self }
+    }
+
+    /// 设置 [`SyncConfig::concurrent_mark`]
+    ///
+    pub fn with_concurrent_mark(self, concurrent_mark: bool) -> Self {
+        Self {
+            concurrent_mark,
+            ..self
+        }
+    }
+}
+
+/// 创建并使用一个线程安全的 GC
+///
+/// 在 `f` 中通过 [`std::thread::scope`] 创建的线程可以共享 [`SyncGc`]
+///
+/// # 恐慌
+///
+/// 如果启用了 [`Config::ambient`]
+///
+pub fn scope_gc_sync<'s, F: for<'gc> FnOnce(SyncGc<'gc, 's>) -> R, R>(
+    config: SyncConfig,
+    f: F,
+) -> R {
+    assert!(
+        !config.gc.ambient,
+        "the ambient GC is not supported by scope_gc_sync"
+    );
+
+    struct Guard<'gc, 's: 'gc>(&'gc SyncGcInner<'gc, 's>);
+
+    impl<'gc, 's: 'gc> Drop for Guard<'gc, 's> {
+        fn drop(&mut self) {
+            unsafe { lock(&self.0.heap).clear_all(self.0.config.gc) };
+        }
+    }
+
+    let inner = SyncGcInner {
+        _marker: PhantomData,
+        config,
+        heap: Mutex::new(SyncHeap {
+            nodes: Vec::with_capacity(config.gc.init_cap),
+            next_id: 0,
+        }),
+        state: Mutex::new(Safepoint {
+            threads: HashMap::new(),
+            stopped: false,
+            collecting: false,
+            cycles: 0,
+        }),
+        cond: Condvar::new(),
+        requested: AtomicBool::new(false),
+        marking: AtomicBool::new(false),
+        satb: Mutex::new(Vec::new()),
+    };
+    let _guard = Guard(&inner);
+    f(SyncGc { inner: &inner })
+}
+
+/// 线程安全的 GC 句柄，可以在线程之间共享，参见 [`crate::gc::Gc`]
+///
+#[derive(Copy, Clone, Debug)]
+pub struct SyncGc<'gc, 's: 'gc> {
+    inner: &'gc SyncGcInner<'gc, 's>,
+}
+
+impl<'gc, 's: 'gc> SyncGc<'gc, 's> {
+    /// 将当前线程注册为 [`Mutator`]
+    ///
+    /// 如果所有 [`Mutator`] 正在被暂停，会等待暂停结束，等待期间新的 [`Mutator`] 视为已到达安全点
+    ///
+    /// 同一个线程可以持有多个 [`Mutator`]，例如在 [`Mutator::blocking`] 中再次注册，只有全部 [`Mutator`] 都到达安全点时线程才会被视为已暂停
+    ///
+    pub fn mutator(self) -> Mutator<'gc, 's> {
+        let inner = self.inner;
+        let mut state = lock(&inner.state);
+        let thread = state.thread();
+        thread.mutators += 1;
+        thread.parked += 1;
+        inner.cond.notify_all();
+        while state.stopped {
+            state = inner.wait(state);
+        }
+        state.thread().parked -= 1;
+        Mutator {
+            _marker: PhantomData,
+            gc: self,
+        }
+    }
+
+    /// 托管一个值，参见 [`crate::gc::Gc::new`]
+    ///
+    /// 托管前会经过一次安全点
+    ///
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<T: SyncTarget + 's>(
+        self,
+        mutator: &Mutator<'gc, 's>,
+        value: T,
+    ) -> SyncRootRef<'gc, SyncNode<'gc, T>> {
+        mutator.safepoint();
+        unsafe {
+            let mut heap = lock(&self.inner.heap);
+            heap.next_id += 1;
+            let node = Box::new(SyncNode::new(value, NodeId::new(heap.next_id)));
+            if self.inner.marking.load(Ordering::SeqCst) {
+                // 标记期间分配的对象直接视为存活
+                node.head.set_marker(Strong);
+            }
+            let r = SyncRootRef::new(transmute::<&'_ SyncNode<'gc, T>, &'gc SyncNode<'gc, T>>(
+                node.deref(),
+            ));
+            heap.nodes
+                .push(NonNull::new_unchecked(Box::into_raw(transmute::<
+                    Box<dyn SyncNodeTrait<'gc> + 'gc>,
+                    Box<dyn SyncNodeTrait<'gc> + 's>,
+                >(
+                    node
+                ))));
+            r
+        }
+    }
+
+    /// 获取当前管理的对象数
+    ///
+    pub fn get_node_count(self) -> usize {
+        lock(&self.inner.heap).nodes.len()
+    }
+
+    /// 确保剩余容量大于 `cap`
+    ///
+    pub fn reserve(self, cap: usize) {
+        lock(&self.inner.heap).nodes.reserve(cap);
+    }
+
+    /// 停止所有 [`Mutator`] 并执行标记清扫，参见 [`crate::gc::Gc::clear`]
+    ///
+    /// 调用者自身必须是 [`Mutator`]，其他线程的 [`Mutator`] 会在到达安全点时暂停，直到回收结束，当前线程持有的其他 [`Mutator`] 不需要等待
+    ///
+    /// 启用 [`SyncConfig::concurrent_mark`] 时，仅在开始标记和结束标记时暂停，标记期间调用者等待辅助线程完成标记
+    ///
+    /// 如果其他线程正在回收，则等待其结束后返回
+    ///
+    pub fn clear(self, mutator: &Mutator<'gc, 's>) {
+        let inner = self.inner;
+        let mut state = lock(&inner.state);
+        if state.collecting {
+            let cycles = state.cycles;
+            state.thread().parked += 1;
+            inner.cond.notify_all();
+            while state.cycles == cycles || state.stopped {
+                state = inner.wait(state);
+            }
+            state.thread().parked -= 1;
+            return;
+        }
+        state.collecting = true;
+        drop(inner.stop(state));
+
+        if inner.config.concurrent_mark {
+            let mut stack = unsafe { lock(&inner.heap).mark_roots(inner.config.gc) };
+            inner.marking.store(true, Ordering::SeqCst);
+            drop(inner.resume(lock(&inner.state)));
+
+            mutator.park(|| {
+                std::thread::scope(|scope| {
+                    scope.spawn(|| unsafe { inner.mark(&mut stack) });
+                });
+            });
+
+            drop(inner.stop(lock(&inner.state)));
+            unsafe {
+                let mut heap = lock(&inner.heap);
+                heap.rescan_roots(&mut stack);
+                inner.mark(&mut stack);
+                inner.marking.store(false, Ordering::SeqCst);
+                heap.sweep(inner.config.gc);
+            }
+        } else {
+            unsafe { lock(&inner.heap).clear(inner.config.gc) };
+        }
+
+        let mut state = inner.resume(lock(&inner.state));
+        state.collecting = false;
+        state.cycles += 1;
+        inner.cond.notify_all();
+    }
+}
+
+/// 代表当前线程正在访问 GC 的令牌
+///
+/// 只有持有令牌的线程才能读写引用位和托管对象
+///
+/// 持有令牌的线程需要定期调用 [`Mutator::safepoint`]，否则其他线程的 [`SyncGc::clear`] 将一直等待
+///
+pub struct Mutator<'gc, 's: 'gc> {
+    _marker: PhantomData<*const ()>,
+    gc: SyncGc<'gc, 's>,
+}
+
+impl<'gc, 's: 'gc> Mutator<'gc, 's> {
+    /// 获取 GC 句柄
+    ///
+    #[inline(always)]
+    pub fn gc(&self) -> SyncGc<'gc, 's> {
+        self.gc
+    }
+
+    /// 安全点，如果其他线程请求回收，则暂停直到回收结束
+    ///
+    #[inline(always)]
+    pub fn safepoint(&self) {
+        if self.gc.inner.requested.load(Ordering::SeqCst) {
+            self.park(|| {});
+        }
+    }
+
+    /// 在不访问 GC 的情况下执行可能长时间阻塞的操作，期间允许其他线程回收
+    ///
+    pub fn blocking<R, F: FnOnce() -> R>(&mut self, f: F) -> R {
+        self.park(f)
+    }
+
+    fn park<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        let inner = self.gc.inner;
+        lock(&inner.state).thread().parked += 1;
+        inner.cond.notify_all();
+        let r = f();
+        let mut state = lock(&inner.state);
+        while state.stopped {
+            state = inner.wait(state);
+        }
+        state.thread().parked -= 1;
+        r
+    }
+
+    /// 写屏障，并发标记期间记录被覆盖或移除的引用
+    ///
+    #[inline(always)]
+    fn barrier<T: ?Sized + SyncNodeTrait<'gc> + 'gc>(&self, old: Option<NonNull<T>>) {
+        if let Some(old) = old {
+            if self.gc.inner.marking.load(Ordering::SeqCst) {
+                lock(&self.gc.inner.satb).push(unsafe { old.as_ref() }.as_dyn_node());
+            }
+        }
+    }
+}
+
+impl<'gc, 's: 'gc> Drop for Mutator<'gc, 's> {
+    fn drop(&mut self) {
+        let mut state = lock(&self.gc.inner.state);
+        let id = current().id();
+        let thread = state.thread();
+        thread.mutators -= 1;
+        if thread.mutators == 0 && thread.parked == 0 {
+            state.threads.remove(&id);
+        }
+        self.gc.inner.cond.notify_all();
+    }
+}
+
+impl<'gc, 's: 'gc> Debug for Mutator<'gc, 's> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mutator").finish()
+    }
+}
+
+/// 一个线程上的 [`Mutator`] 情况
+///
+#[derive(Default)]
+struct MutatorThread {
+    /// 持有的 [`Mutator`] 数
+    mutators: usize,
+
+    /// 正处于安全点的 [`Mutator`] 数
+    parked: usize,
+}
+
+struct Safepoint {
+    threads: HashMap<ThreadId, MutatorThread>,
+    stopped: bool,
+    collecting: bool,
+    cycles: u64,
+}
+
+impl Safepoint {
+    /// 当前线程的情况
+    ///
+    fn thread(&mut self) -> &mut MutatorThread {
+        self.threads.entry(current().id()).or_default()
+    }
+
+    /// 除当前线程外，其他线程持有的 [`Mutator`] 是否都已到达安全点
+    ///
+    fn others_parked(&self) -> bool {
+        let id = current().id();
+        self.threads
+            .iter()
+            .all(|(&i, thread)| i == id || thread.parked == thread.mutators)
+    }
+}
+
+struct SyncHeap<'gc, 's: 'gc> {
+    nodes: Vec<NonNull<dyn SyncNodeTrait<'gc> + 's>>,
+    next_id: u64,
+}
+
+unsafe impl<'gc, 's: 'gc> Send for SyncHeap<'gc, 's> {}
+
+impl<'gc, 's: 'gc> SyncHeap<'gc, 's> {
+    unsafe fn clear(&mut self, config: Config) {
+        let mut stack = self.mark_roots(config);
+        while let Some(r) = stack.pop() {
+            r.mark_and_collect(&mut stack);
+        }
+        self.sweep(config);
+    }
+
+    /// 重置标记并收集根对象
+    ///
+    unsafe fn mark_roots(&self, config: Config) -> Vec<&'gc dyn SyncNodeTrait<'gc>> {
+        let mut stack =
+            Vec::with_capacity((self.nodes.len() as f32 * config.stack_factor) as usize);
+        for &r in self.nodes.iter() {
+            let r: &'gc dyn SyncNodeTrait<'gc> = &*r.as_ptr();
+            if r.root() != 0 {
+                r.head().set_marker(Trace);
+                stack.push(r);
+            } else {
+                r.head().set_marker(Unknown);
+            }
+        }
+        stack
+    }
+
+    /// 收集标记期间新出现的根对象
+    ///
+    unsafe fn rescan_roots(&self, stack: &mut Vec<&'gc dyn SyncNodeTrait<'gc>>) {
+        for &r in self.nodes.iter() {
+            let r: &'gc dyn SyncNodeTrait<'gc> = &*r.as_ptr();
+            if r.root() != 0 {
+                trace(r, stack);
+            }
+        }
+    }
+
+    unsafe fn sweep(&mut self, config: Config) {
+        let nodes = take(&mut self.nodes);
+        if config.pre_drop {
+            for &i in nodes.iter() {
+                if SyncNodeHead::from_node_trait(i.as_ref()).get_marker() == Unknown {
+                    i.as_ref().pre_drop();
+                }
+            }
+        }
+        let mut new_nodes = nodes
+            .into_iter()
+            .filter(
+                |&i| match SyncNodeHead::from_node_trait(i.as_ref()).get_marker() {
+                    Unknown => {
+                        drop(Box::from_raw(i.as_ptr()));
+                        false
+                    }
+                    Strong => true,
+                    Trace => unreachable!(),
+                },
+            )
+            .collect::<Vec<_>>();
+        new_nodes.reserve(config.init_cap);
+        swap(&mut self.nodes, &mut new_nodes);
+    }
+
+    unsafe fn clear_all(&mut self, config: Config) {
+        if config.pre_drop {
+            for node in self.nodes.iter() {
+                node.as_ref().pre_drop();
+            }
+        }
+        for node in self.nodes.iter() {
+            drop(Box::from_raw(node.as_ptr()));
+        }
+        self.nodes.clear();
+    }
+}
+
+struct SyncGcInner<'gc, 's: 'gc> {
+    _marker: PhantomData<fn(&'gc ()) -> &'gc ()>,
+    config: SyncConfig,
+    heap: Mutex<SyncHeap<'gc, 's>>,
+    state: Mutex<Safepoint>,
+    cond: Condvar,
+    requested: AtomicBool,
+    marking: AtomicBool,
+    satb: Mutex<Vec<&'gc dyn SyncNodeTrait<'gc>>>,
+}
+
+impl<'gc, 's: 'gc> SyncGcInner<'gc, 's> {
+    fn wait<'a>(&self, state: MutexGuard<'a, Safepoint>) -> MutexGuard<'a, Safepoint> {
+        self.cond
+            .wait(state)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 请求暂停，并等待其他线程的 [`Mutator`] 到达安全点
+    ///
+    fn stop<'a>(&self, mut state: MutexGuard<'a, Safepoint>) -> MutexGuard<'a, Safepoint> {
+        state.stopped = true;
+        self.requested.store(true, Ordering::SeqCst);
+        while !state.others_parked() {
+            state = self.wait(state);
+        }
+        state
+    }
+
+    /// 结束暂停
+    ///
+    fn resume<'a>(&self, mut state: MutexGuard<'a, Safepoint>) -> MutexGuard<'a, Safepoint> {
+        state.stopped = false;
+        self.requested.store(false, Ordering::SeqCst);
+        self.cond.notify_all();
+        state
+    }
+
+    /// 追踪直到栈和写屏障记录均为空
+    ///
+    unsafe fn mark(&self, stack: &mut Vec<&'gc dyn SyncNodeTrait<'gc>>) {
+        loop {
+            while let Some(r) = stack.pop() {
+                r.mark_and_collect(stack);
+            }
+            let satb = take(&mut *lock(&self.satb));
+            if satb.is_empty() {
+                break;
+            }
+            for r in satb {
+                trace(r, stack);
+            }
+        }
+    }
+}
+
+impl<'gc, 's: 'gc> Debug for SyncGcInner<'gc, 's> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncGcInner")
+            .field("config", &self.config)
+            .field("nodes", &lock(&self.heap).nodes.len())
+            .finish()
+    }
+}
@@ -0,0 +1,427 @@
+use crate::gc::NoGcGuard;
+use crate::gc_cell::GcCell;
+use crate::node::{Node, NodeHead, NodeTrait, State};
+use crate::root_ref::RootRef;
+use crate::target::{RefSet, Target};
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// 可以被直接追踪的值，值自身持有指向被管理对象的引用
+///
+/// 与 [`Target`] 将引用放在单独的引用集合中不同，实现此特征的值可以在任意位置持有 [`TraceRef`]
+///
+/// 所有 [`RefSet`] 都实现了此特征，因此现有的引用集合也可以作为值的一部分
+///
+/// 托管之后的值可以通过 [`Node::traced_mut`] 修改，[`std::cell::RefCell`] 能够在不释放引用的情况下移出其内容，因此没有实现此特征
+///
+/// # 安全
+///
+/// `trace`、`adopt` 和 `release` 必须不重不漏的遍历值内部的全部引用
+///
+/// 值的 [`Drop::drop`] 不得访问内部的引用，需要访问引用的清理逻辑应当放在 `pre_drop` 中
+///
+pub unsafe trait Trace<'gc> {
+    /// 追踪内部引用
+    ///
+    /// 这将会在清理时由 GC 调用
+    ///
+    /// # 安全
+    ///
+    /// 用户不得调用
+    ///
+    unsafe fn trace(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>);
+
+    /// 值进入 GC 管理时调用，释放值在托管之前持有的根引用
+    ///
+    /// # 安全
+    ///
+    /// 用户不得调用
+    ///
+    unsafe fn adopt(&self);
+
+    /// 值被可变借用之前调用，重新获得 `adopt` 所释放的根引用，使得移出值的引用依然有效
+    ///
+    /// # 安全
+    ///
+    /// 用户不得调用
+    ///
+    unsafe fn release(&self);
+
+    /// 预析构函数，参见 [`Target::pre_drop`]
+    ///
+    /// # 安全
+    ///
+    /// 用户不得调用
+    ///
+    #[inline(always)]
+    unsafe fn pre_drop(&self) {}
+}
+
+unsafe impl<'gc, T: RefSet<'gc>> Trace<'gc> for T {
+    #[inline(always)]
+    unsafe fn trace(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+        self.collect(stack);
+    }
+
+    #[inline(always)]
+    unsafe fn adopt(&self) {}
+
+    #[inline(always)]
+    unsafe fn release(&self) {}
+}
+
+macro_rules! impl_trace_for_plain {
+    ($($T:ty),*) => {
+        $(
+            unsafe impl<'gc> Trace<'gc> for $T {
+                #[inline(always)]
+                unsafe fn trace(&self, _stack: &mut Vec<&dyn NodeTrait<'gc>>) {}
+
+                #[inline(always)]
+                unsafe fn adopt(&self) {}
+
+                #[inline(always)]
+                unsafe fn release(&self) {}
+            }
+        )*
+    };
+}
+
+impl_trace_for_plain!(
+    bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, String
+);
+
+macro_rules! impl_trace_for_iter {
+    ($($T:ident),*) => {
+        $(
+            unsafe impl<'gc, T: Trace<'gc>> Trace<'gc> for $T<T> {
+                #[inline(always)]
+                unsafe fn trace(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+                    for i in self {
+                        i.trace(stack);
+                    }
+                }
+
+                #[inline(always)]
+                unsafe fn adopt(&self) {
+                    for i in self {
+                        i.adopt();
+                    }
+                }
+
+                #[inline(always)]
+                unsafe fn release(&self) {
+                    for i in self {
+                        i.release();
+                    }
+                }
+
+                #[inline(always)]
+                unsafe fn pre_drop(&self) {
+                    for i in self {
+                        i.pre_drop();
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_trace_for_iter!(Vec, VecDeque);
+
+unsafe impl<'gc, T: Trace<'gc>> Trace<'gc> for Option<T> {
+    #[inline(always)]
+    unsafe fn trace(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+        if let Some(i) = self {
+            i.trace(stack);
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn adopt(&self) {
+        if let Some(i) = self {
+            i.adopt();
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn release(&self) {
+        if let Some(i) = self {
+            i.release();
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn pre_drop(&self) {
+        if let Some(i) = self {
+            i.pre_drop();
+        }
+    }
+}
+
+/// 内部的值只能通过 [`GcCell::get_mut`] 修改，因此不会在不释放引用的情况下被移出
+///
+unsafe impl<'gc, T: Trace<'gc>> Trace<'gc> for GcCell<T> {
+    #[inline(always)]
+    unsafe fn trace(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+        self.borrow().trace(stack);
+    }
+
+    #[inline(always)]
+    unsafe fn adopt(&self) {
+        self.borrow().adopt();
+    }
+
+    #[inline(always)]
+    unsafe fn release(&self) {
+        self.borrow().release();
+    }
+
+    #[inline(always)]
+    unsafe fn pre_drop(&self) {
+        self.borrow().pre_drop();
+    }
+}
+
+/// 可追踪值的类型族，将带有 `'gc` 的值类型与不带有 `'gc` 的标记类型关联
+///
+/// 托管时标记类型需要满足 [`crate::gc::Gc::new`] 的存活要求
+///
+pub trait Traced {
+    /// 值的类型
+    type Value<'gc>: Trace<'gc>;
+}
+
+/// 将 [`Traced`] 适配为 [`Target`]，值存储在引用集合中，参见 [`crate::gc::Gc::new_traced`]
+///
+pub struct TraceType<F: Traced>(PhantomData<F>);
+
+impl<F: Traced> TraceType<F> {
+    pub(crate) fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<F: Traced> Target for TraceType<F> {
+    type RefObject<'gc> = TraceCell<'gc, F>;
+
+    #[inline(always)]
+    unsafe fn pre_drop<'gc>(&self, ref_set: &Self::RefObject<'gc>) {
+        if let Some(value) = ref_set.value().as_ref() {
+            value.pre_drop();
+        }
+    }
+}
+
+/// [`TraceType`] 的引用集合，持有被追踪的值
+///
+pub struct TraceCell<'gc, F: Traced> {
+    cell: RefCell<Option<F::Value<'gc>>>,
+}
+
+impl<'gc, F: Traced> TraceCell<'gc, F> {
+    /// 写入值并释放其在托管之前持有的根引用
+    ///
+    pub(crate) fn init(&self, value: F::Value<'gc>) {
+        let mut cell = self.cell.borrow_mut();
+        assert!(cell.is_none());
+        unsafe { cell.insert(value).adopt() };
+    }
+
+    /// 回收期间访问值
+    ///
+    /// # 恐慌
+    ///
+    /// 可变借用期间无法回收，只有泄漏的 [`TracedMut`] 会使值在此时仍被借用，此时恐慌
+    ///
+    fn value(&self) -> Ref<'_, Option<F::Value<'gc>>> {
+        self.cell
+            .try_borrow()
+            .expect("traced value is still borrowed during collection")
+    }
+}
+
+impl<'gc, F: Traced> Debug for TraceCell<'gc, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TraceCell")
+            .field("init", &self.cell.try_borrow().map(|v| v.is_some()))
+            .finish()
+    }
+}
+
+unsafe impl<'gc, F: Traced> RefSet<'gc> for TraceCell<'gc, F> {
+    #[inline(always)]
+    unsafe fn build() -> Self {
+        Self {
+            cell: RefCell::new(None),
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn collect(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+        if let Some(value) = self.value().as_ref() {
+            value.trace(stack);
+        }
+    }
+}
+
+impl<'gc, F: Traced> Node<'gc, TraceType<F>> {
+    /// 获取被追踪的值
+    ///
+    /// # 恐慌
+    ///
+    /// 如果值正在通过 [`Node::traced_mut`] 被修改则恐慌
+    ///
+    #[inline(always)]
+    pub fn traced(&self) -> Ref<'_, F::Value<'gc>> {
+        Ref::map(self.ref_set.cell.borrow(), |v| v.as_ref().unwrap())
+    }
+
+    /// 在禁止回收期间修改被追踪的值
+    ///
+    /// 借用期间值内部的引用重新成为根引用，因此可以将其移出值，借用结束时留在值内部的引用再次交由值追踪
+    ///
+    /// # 恐慌
+    ///
+    /// 如果值正在被借用则恐慌
+    ///
+    pub fn traced_mut<'r>(&'r self, _guard: &'r NoGcGuard<'_, 'gc, '_>) -> TracedMut<'r, 'gc, F> {
+        let value = RefMut::map(self.ref_set.cell.borrow_mut(), |v| v.as_mut().unwrap());
+        unsafe { value.release() };
+        TracedMut { value }
+    }
+}
+
+/// 被追踪的值的可变借用，参见 [`Node::traced_mut`]
+///
+pub struct TracedMut<'r, 'gc, F: Traced> {
+    value: RefMut<'r, F::Value<'gc>>,
+}
+
+impl<'r, 'gc, F: Traced> Deref for TracedMut<'r, 'gc, F> {
+    type Target = F::Value<'gc>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<'r, 'gc, F: Traced> DerefMut for TracedMut<'r, 'gc, F> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<'r, 'gc, F: Traced> Drop for TracedMut<'r, 'gc, F> {
+    fn drop(&mut self) {
+        unsafe { self.value.adopt() };
+    }
+}
+
+/// 位于可追踪值内部的引用
+///
+/// 在值被托管之前，引用的对象被视为根引用，托管之后则由所在的值追踪
+///
+pub struct TraceRef<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> {
+    _marker: PhantomData<*mut &'gc ()>,
+    cell: Cell<Option<NonNull<T>>>,
+    managed: Cell<bool>,
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> TraceRef<'gc, T> {
+    pub fn new(r: Option<&T>) -> Self {
+        if let Some(r) = r {
+            NodeHead::from_node_trait(r).inc_root();
+        }
+        Self {
+            _marker: PhantomData,
+            cell: Cell::new(r.map(NonNull::from)),
+            managed: Cell::new(false),
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self) -> Option<RootRef<'gc, T>> {
+        self.cell.get().map(|r| RootRef::new(unsafe { r.as_ref() }))
+    }
+
+    #[inline(always)]
+    pub fn set(&self, r: Option<&T>) {
+        if !self.managed.get() {
+            if let Some(r) = r {
+                NodeHead::from_node_trait(r).inc_root();
+            }
+            if let Some(old) = self.cell.get() {
+                NodeHead::from_node_trait(unsafe { old.as_ref() }).dec_root();
+            }
+        }
+        self.cell.set(r.map(NonNull::from));
+    }
+
+    /// 所在的值是否已经被托管
+    ///
+    #[inline(always)]
+    pub fn is_managed(&self) -> bool {
+        self.managed.get()
+    }
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc> + 'gc> Drop for TraceRef<'gc, T> {
+    fn drop(&mut self) {
+        // 托管之后引用的对象可能已经被回收，此时不得访问
+        if !self.managed.get() {
+            if let Some(r) = self.cell.get() {
+                NodeHead::from_node_trait(unsafe { r.as_ref() }).dec_root();
+            }
+        }
+    }
+}
+
+impl<'gc, T: ?Sized + NodeTrait<'gc>> Debug for TraceRef<'gc, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_tuple("TraceRef");
+        if let Some(r) = self.cell.get() {
+            s.field(&r);
+        } else {
+            s.field(&None::<()>);
+        }
+        s.finish()
+    }
+}
+
+unsafe impl<'gc, T: ?Sized + NodeTrait<'gc>> Trace<'gc> for TraceRef<'gc, T> {
+    #[inline(always)]
+    unsafe fn trace(&self, stack: &mut Vec<&dyn NodeTrait<'gc>>) {
+        if let Some(r) = self.cell.get() {
+            let r = r.as_ref();
+            if NodeHead::from_node_trait(r).get_marker() == State::Unknown {
+                NodeHead::from_node_trait(r).set_marker(State::Trace);
+                stack.push(r.as_dyn_node());
+            }
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn adopt(&self) {
+        if !self.managed.replace(true) {
+            if let Some(r) = self.cell.get() {
+                NodeHead::from_node_trait(r.as_ref()).dec_root();
+            }
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn release(&self) {
+        if self.managed.replace(false) {
+            if let Some(r) = self.cell.get() {
+                NodeHead::from_node_trait(r.as_ref()).inc_root();
+            }
+        }
+    }
+}